@@ -27,7 +27,7 @@ async fn minify_css(
     let level = Level::from_str(&request.level).unwrap_or(Level::One);
     let output_css = minifier
         .minify(&request.input_css, level)
-        .unwrap_or_else(|e| e.to_string());
+        .unwrap_or_else(|e| format!("{}\n{}", e, e.snippet()));
 
     match Template::call(&IndexTemplate {
         input_css: Some(request.into_inner().input_css),
@@ -1,2 +1,2 @@
-pub const STYLES: &str = "html{line-height:1.15;-webkit-text-size-adjust:100%}body{margin:0;font-family:'Oswald',sans-serif}main{display:block}h1{font-size:2em;margin:.67em 0}hr{box-sizing:content-box;height:0;overflow:visible}pre{font:1em monospace,monospace}a{background:transparent}abbr[title]{border-bottom:none;text-decoration:underline dotted}b,strong{font-weight:700er}code,kbd,samp{font:1em monospace,monospace}small{font-size:80%}sub,sup{font-size:75%;line-height:0;position:relative;vertical-align:baseline}sub{bottom:-0.25em}sup{top:-0.5em}img{border:none}button,input,optgroup,select,textarea{margin:0;font:100%/1.15 inherit}button,input{overflow:visible}button,select{text-transform:none}[type=\"button\"],[type=\"reset\"],[type=\"submit\"],button{-webkit-appearance:button}[type=\"button\"]::-moz-focus-inner,[type=\"reset\"]::-moz-focus-inner,[type=\"submit\"]::-moz-focus-inner ,button::-moz-focus-inner{border:none;padding:0}[type=\"button\"]:-moz-focusring,[type=\"reset\"]:-moz-focusring,[type=\"submit\"]:-moz-focusring ,button:-moz-focusring{outline:1px dotted ButtonText}fieldset{padding:.35em .75em .625em}legend{box-sizing:border-box;color:inherit;display:table;max-width:100%;padding:0;white-space:normal}progress{vertical-align:baseline}textarea{overflow:auto}[type=\"checkbox\"],[type=\"radio\"]{box-sizing:border-box;padding:0}[type=\"number\"]::-webkit-inner-spin-button,[type=\"number\"]::-webkit-outer-spin-button {height:auto}[type=\"search\"]{-webkit-appearance:textfield;outline-offset:-2px}[type=\"search\"]::-webkit-search-decoration {-webkit-appearance:none}::-webkit-file-upload-button {-webkit-appearance:button;font:inherit}details{display:block}summary{display:list-item}template{display:none}[hidden]{display:none}.header{display:flex;justify-content:space-around;align-items:center;padding:1rem 0;background:#bdbdbd}.nav{display:flex}.brand{margin-right:1rem;font-size:20px;font-weight:700;text-decoration:none;color:#000}.main{background:#efefef}.container{width:100%;margin:0 auto;padding:20px 0}.heading{padding:1rem 0;margin:0}.columns{display:grid;grid-template-columns:1fr}.column{padding:10px 20px}.textarea{width:100%;border:none;min-width:50%;font:14px/16px 'Oswald',monospace;box-sizing:border-box;padding:16px}textarea:focus {outline:none;box-shadow:0 13px 38px 0 #212121}textarea:disabled {border:1px solid #212121;border-radius:10px}.code-block{background:#212121;color:#fafafa;padding:20px;max-width:100%;white-space:pre-wrap}.footer{padding:10px;display:flex;justify-content:center;align-items:center;background:#8c8c8c}.link{color:black;text-decoration:none}.submit{background:#212121;color:#fafafa;border:none;padding:15px 32px;text-align:center;text-decoration:none;font-size:16px;cursor:pointer;margin:10px 0}.options{display:flex;justify-content:space-between;align-items:flex-start;flex-direction:column}@media (min-width:576px){.container{max-width:540px}}@media (min-width:768px){.container{max-width:720px}.options{flex-direction:row;align-items:center}.submit{margin:0}}@media (min-width:992px){.container{max-width:960px}.columns{grid-template-columns:1fr 1fr}}@media (min-width:1200px){.container{max-width:1200px}}";
+pub const STYLES: &str = "html{line-height:1.15;-webkit-text-size-adjust:100%}body{margin:0;font-family:'Oswald',sans-serif}main,details{display:block}h1{font-size:2em;margin:.67em 0}hr{box-sizing:content-box;height:0;overflow:visible}pre,code,kbd,samp{font:1em monospace,monospace}a{background:transparent}abbr[title]{border-bottom:none;text-decoration:underline;text-decoration:underline dotted}b,strong{font-weight:700er}small{font-size:80%}sub,sup{font-size:75%;line-height:0;position:relative;vertical-align:baseline}sub{bottom:-.25em}sup{top:-.5em}img{border:none}button,input,optgroup,select,textarea{font:100%/1.15 inherit;margin:0}button,input{overflow:visible}button,select{text-transform:none}button,[type=button],[type=reset],[type=submit]{-webkit-appearance:button}button::-moz-focus-inner,[type=button]::-moz-focus-inner,[type=reset]::-moz-focus-inner,[type=submit]::-moz-focus-inner{border:none;padding:0}button:-moz-focusring,[type=button]:-moz-focusring,[type=reset]:-moz-focusring,[type=submit]:-moz-focusring{outline:1px dotted ButtonText}fieldset{padding:.35em .75em .625em}legend{box-sizing:border-box;color:inherit;display:table;max-width:100%;padding:0;white-space:normal}progress{vertical-align:baseline}textarea{overflow:auto}[type=checkbox],[type=radio]{box-sizing:border-box;padding:0}[type=number]::-webkit-inner-spin-button,[type=number]::-webkit-outer-spin-button{height:auto}[type=search]{-webkit-appearance:textfield;outline-offset:-2px}[type=search]::-webkit-search-decoration{-webkit-appearance:none}::-webkit-file-upload-button{-webkit-appearance:button;font:inherit}summary{display:list-item}template,[hidden]{display:none}.header{justify-content:space-around;align-items:center;padding:1rem 0;background:#bdbdbd}.header,.nav{display:flex}.brand{margin-right:1rem;font-size:20px;font-weight:700;text-decoration:none;color:#000}.main{background:#efefef}.container{width:100%;margin:0 auto;padding:20px 0}@media (min-width:576px){.container{max-width:540px}}@media (min-width:768px){.container{max-width:45pc}}@media (min-width:992px){.container{max-width:10in}}@media (min-width:1200px){.container{max-width:75pc}}.heading{padding:1rem 0;margin:0}.columns{display:grid;grid-template-columns:1fr}@media (min-width:992px){.columns{grid-template-columns:1fr 1fr}}.column{padding:10px 20px}.textarea{width:100%;font:14px/1pc 'Oswald',monospace;min-width:50%;padding:1pc;border:none;box-sizing:border-box}textarea:focus{outline:none;box-shadow:0 13px 38px 0 #212121}textarea:disabled{border:1px solid #212121;border-radius:10px}.code-block{background:#212121;color:#fafafa;padding:20px;max-width:100%;white-space:pre-wrap}.footer{padding:10px;display:flex;justify-content:center;align-items:center;background:#8c8c8c}.link{color:#000;text-decoration:none}.submit{background:#212121;color:#fafafa;border:none;padding:15px 2pc;text-align:center;text-decoration:none;font-size:1pc;cursor:pointer;margin:10px 0}.options{display:flex;justify-content:space-between;align-items:flex-start;flex-direction:column}@media (min-width:768px){.options{flex-direction:row;align-items:center}.submit{margin:0}}";
 pub const STYLES_HASH: &str = "3476900567878811119";
@@ -1,7 +1,10 @@
 use clap::Parser;
 use css_minify::optimizations::{Level, Minifier};
+use css_minify::source_map::{write_with_source_map, SourceMap, SourceMapOptions, SourceMapUrl};
 use indoc::indoc;
 use std::fs::{read_to_string, write};
+use std::path::Path;
+use std::process::exit;
 
 #[derive(Parser)]
 #[command(version = "0.1", author = "Mnwa")]
@@ -19,11 +22,56 @@ struct Opts {
                 0 - Without optimizations 
                 1 - Remove whitespaces, replace `0.` to `.` and others non dangerous optimizations
                 2 - Level One + shortcuts (margins, paddings, backgrounds and etc). In mostly cases it's non dangerous optimizations, but be careful
-                3 - Level Two + merge @media and css blocks with equal screen/selectors. It is a danger optimizations, because ordering of your css code may be changed.
+                3 - Level Two + merge @media, @container and css blocks with equal screen/selectors or declarations. It is a danger optimizations, because ordering of your css code may be changed.
         "},
         default_value = "1"
     )]
     level: Level,
+
+    #[arg(
+        long,
+        help = "rewrite nested rules into plain selectors for browsers without css nesting support"
+    )]
+    flatten_nesting: bool,
+
+    #[arg(
+        long,
+        help = "sort selectors of every selector list, it helps gzip but changes the source order"
+    )]
+    sort_selectors: bool,
+
+    #[arg(
+        long,
+        help = "skip invalid declarations and rules like browsers do and print them as warnings"
+    )]
+    tolerant: bool,
+
+    #[arg(
+        long,
+        default_value = "1",
+        help = "transform rules of the input on the given number of threads"
+    )]
+    threads: usize,
+
+    #[arg(
+        long,
+        requires = "output",
+        help = "write the source map next to the output file with the `.map` extension"
+    )]
+    source_map: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "source_map",
+        help = "embed the source map into the minified css"
+    )]
+    inline_source_map: bool,
+
+    #[arg(
+        long,
+        help = "source map of the input from a preprocessor, the written source map points to its sources"
+    )]
+    input_source_map: Option<String>,
 }
 
 fn main() {
@@ -31,8 +79,18 @@ fn main() {
         input,
         output,
         level,
+        flatten_nesting,
+        sort_selectors,
+        tolerant,
+        threads,
+        source_map,
+        inline_source_map,
+        input_source_map,
     } = Opts::parse();
-    let minifier = Minifier::default();
+    let minifier = Minifier::default()
+        .flatten_nesting(flatten_nesting)
+        .sort_selectors(sort_selectors)
+        .threads(threads);
 
     let input_file = read_to_string(
         shellexpand::full(&input)
@@ -40,9 +98,71 @@ fn main() {
             .to_string(),
     )
     .expect("cannot open input file");
-    let minified_css = minifier.minify(&input_file, level).unwrap();
+    let stylesheet = if tolerant {
+        let (stylesheet, diagnostics) = css_minify::parse_tolerant(&input_file);
+        for e in diagnostics {
+            eprintln!("warning: {}\n{}", e, e.snippet());
+        }
+        stylesheet
+    } else {
+        match css_minify::parse(&input_file) {
+            Ok(stylesheet) => stylesheet,
+            Err(e) => {
+                eprintln!("{}\n{}", e, e.snippet());
+                exit(1)
+            }
+        }
+    };
+    let stylesheet = minifier.optimize(stylesheet, level);
+
+    let output = output.map(|output| {
+        shellexpand::full(&output)
+            .expect("fail to parse output path")
+            .to_string()
+    });
+    let file_name = |path: &str| {
+        Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+    };
+    let map_path = output
+        .as_ref()
+        .filter(|_| source_map)
+        .map(|output| format!("{}.map", output));
+    let url = match &map_path {
+        Some(map_path) => SourceMapUrl::External(file_name(map_path).unwrap_or_default()),
+        None if inline_source_map => SourceMapUrl::Inline,
+        None => SourceMapUrl::None,
+    };
+    let minified_css = if url == SourceMapUrl::None {
+        stylesheet.to_string()
+    } else {
+        let options = SourceMapOptions {
+            source: file_name(&input).unwrap_or_default(),
+            file: output.as_deref().and_then(file_name),
+            include_content: true,
+            url,
+            input_map: input_source_map.map(|path| {
+                let json = read_to_string(
+                    shellexpand::full(&path)
+                        .expect("fail to parse input source map path")
+                        .to_string(),
+                )
+                .expect("cannot open input source map file");
+                SourceMap::from_json(&json).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    exit(1)
+                })
+            }),
+        };
+        let (minified_css, source_map) = write_with_source_map(&stylesheet, &input_file, &options);
+        if let Some(map_path) = &map_path {
+            write(map_path, source_map.to_json()).expect("cannot open or create the map file");
+        }
+        minified_css
+    };
 
-    let mut size_diff = input_file.len() - minified_css.len();
+    let mut size_diff = input_file.len().saturating_sub(minified_css.len());
     let size_rate = ((size_diff as f64) / (input_file.len() as f64) * 100f64) as i64;
     let mut prefix = "bytes";
     if size_diff > 1024 {
@@ -52,13 +172,7 @@ fn main() {
     println!("You saved: {}% ({} {})", size_rate, size_diff, prefix);
 
     if let Some(output) = output {
-        write(
-            shellexpand::full(&output)
-                .expect("fail to parse output path")
-                .to_string(),
-            minified_css,
-        )
-        .expect("cannot open or create the output file");
+        write(output, minified_css).expect("cannot open or create the output file");
     } else {
         println!("{}", minified_css)
     }
@@ -0,0 +1,15 @@
+#!/bin/sh
+# Downloads the real-world stylesheets measured by `cargo bench -p css-minify`
+# into `benches/fixtures`. Versions are pinned, so the results stay comparable.
+set -eu
+
+fixtures="$(dirname "$0")/fixtures"
+mkdir -p "$fixtures"
+
+fetch() {
+    echo "$2"
+    curl -fsSL "$2" -o "$fixtures/$1"
+}
+
+fetch bootstrap-5.3.3.css https://cdn.jsdelivr.net/npm/bootstrap@5.3.3/dist/css/bootstrap.css
+fetch tailwind-2.2.19.css https://cdn.jsdelivr.net/npm/tailwindcss@2.2.19/dist/tailwind.css
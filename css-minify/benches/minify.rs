@@ -0,0 +1,89 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use css_minify::optimizations::{Level, Minifier};
+use std::fs;
+use std::path::Path;
+
+/// Stylesheets to measure: the bundled web page styles, a generated stylesheet
+/// and every `*.css` placed into `benches/fixtures`, see `benches/fetch-fixtures.sh`
+fn stylesheets() -> Vec<(String, String)> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut stylesheets = vec![
+        (
+            "main.css".to_string(),
+            fs::read_to_string(root.join("../css-minifier-web/static/main.css"))
+                .expect("web styles are readable"),
+        ),
+        ("generated.css".to_string(), generated(2000)),
+    ];
+    if let Ok(entries) = fs::read_dir(root.join("benches/fixtures")) {
+        let mut fixtures = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|e| e == "css"))
+            .collect::<Vec<_>>();
+        fixtures.sort();
+        for path in fixtures {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            stylesheets.push((
+                name,
+                fs::read_to_string(&path).expect("fixture is readable"),
+            ));
+        }
+    }
+    stylesheets
+}
+
+/// Framework-like stylesheet with utility classes, components and media queries
+fn generated(rules: usize) -> String {
+    let mut css =
+        String::from("@charset \"utf-8\";\n:root { --primary: #0D6EFD; --gap: 0.50rem }\n");
+    for i in 0..rules {
+        css.push_str(&format!(
+            r#"
+.btn-{i}, .btn-{i}:hover > .icon:not(.disabled) {{
+    display: inline-block;
+    padding: 0.375rem 0.75rem 0.375rem 0.75rem;
+    gap: var(--gap);
+    margin: 0px 0px 0px 0px;
+    font-weight: normal;
+    color: rgb(255, 255, 255);
+    background-color: #FFFFFF;
+    border: 1px solid transparent;
+    transition: color .15s ease-in-out, background-color .15s ease-in-out;
+}}
+@media (min-width: {width}px) {{
+    .col-{i} {{ flex: 0 0 auto; width: calc(100% / {i} - 1rem); }}
+}}
+"#,
+            width = 576 + i % 4 * 192,
+        ));
+    }
+    css
+}
+
+fn minify(c: &mut Criterion) {
+    let minifier = Minifier::default();
+    let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+    let parallel = Minifier::default().threads(threads);
+    for (name, css) in stylesheets() {
+        let mut group = c.benchmark_group(name);
+        group.throughput(Throughput::Bytes(css.len() as u64));
+        group.bench_function("parse", |b| {
+            b.iter(|| css_minify::parse(&css).expect("valid css"))
+        });
+        for level in [Level::One, Level::Three] {
+            group.bench_with_input(
+                BenchmarkId::new("minify", format!("{:?}", level)),
+                &level,
+                |b, &level| b.iter(|| minifier.minify(&css, level).expect("valid css")),
+            );
+        }
+        group.bench_function(BenchmarkId::new("minify", "Three/parallel"), |b| {
+            b.iter(|| parallel.minify(&css, Level::Three).expect("valid css"))
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, minify);
+criterion_main!(benches);
@@ -20,10 +20,147 @@
 //!          "#,
 //!          Level::Three
 //!     ),
-//!     Ok("#some_id,input{padding:5px 3px;color:white}#some_id_2,.class{padding:5px 4px;color:#fff}".into())
+//!     Ok("#some_id,input{padding:5px 3px;color:#fff}#some_id_2,.class{padding:5px 4px;color:#fff}".into())
 //! )
 //! ```
 
 pub mod optimizations;
 pub(crate) mod parsers;
-pub(crate) mod structure;
+pub mod source_map;
+pub mod structure;
+
+use crate::optimizations::{failed_entity, MError};
+use crate::parsers::css_entity::{parse_css, parse_entities};
+use crate::parsers::recovery::{close_at_eof, skip_declaration, skip_rule};
+use crate::structure::{IntoOwned, Stylesheet};
+use std::ops::Range;
+
+/// Parse css input into the [`Stylesheet`] tree without applying any optimizations.
+/// The tree may be inspected or rewritten and then serialized back with `to_string()`.
+///
+/// ```rust
+/// use css_minify::structure::CssEntity;
+/// let mut stylesheet = css_minify::parse("a { color: red } @media print { a { color: black } }").unwrap();
+/// stylesheet.retain(|entity| !matches!(entity, CssEntity::Media(_)));
+/// assert_eq!(stylesheet.to_string(), "a{color:red}");
+/// ```
+pub fn parse(input: &str) -> Result<Stylesheet<'_>, MError> {
+    parse_css(input)
+        .map(|(_, mut entities)| {
+            entities.relocate(&|remaining| input.len() - remaining);
+            entities
+        })
+        .map_err(|e| MError::new(input, e))
+}
+
+/// Parse css input like browsers do: invalid declarations and rules are skipped
+/// and parsing continues after them, strings, urls and blocks which are not closed are closed
+/// at the end of the input.
+/// Every skipped part is returned as the diagnostic.
+///
+/// ```rust
+/// let (stylesheet, diagnostics) = css_minify::parse_tolerant("a { color: red;; margin: } b { color: blue }");
+/// assert_eq!(stylesheet.to_string(), "a{color:red}b{color:blue}");
+/// assert_eq!(diagnostics.len(), 1);
+/// ```
+pub fn parse_tolerant(input: &str) -> (Stylesheet<'_>, Vec<MError>) {
+    let mut stylesheet = Stylesheet::default();
+    let mut diagnostics = vec![];
+    let mut rest = input;
+    // offset of the last invalid rule with its line
+    let mut line = (0, 1);
+    loop {
+        if let Ok((next, mut entities)) = parse_entities(rest) {
+            entities.relocate(&|remaining| input.len() - remaining);
+            stylesheet.append(&mut entities);
+            rest = next;
+        }
+        if rest.is_empty() {
+            return (stylesheet, diagnostics);
+        }
+        // only the invalid rule is parsed again, valid rules after it are parsed from the input
+        let start = input.len() - rest.len();
+        // at least one character is skipped, so every iteration moves forward
+        let len = skip_rule(rest).max(rest.chars().next().map_or(0, char::len_utf8));
+        line = (start, line.1 + input[line.0..start].matches('\n').count());
+        let mut entities = recover(input, start..start + len, line.1, &mut diagnostics);
+        stylesheet.append(&mut entities);
+        rest = &rest[len..];
+    }
+}
+
+/// Skips the invalid parts of the rule at the `range` of the input, which starts at the `line`,
+/// and closes the strings, urls and blocks which are open at its end
+fn recover(
+    input: &str,
+    range: Range<usize>,
+    line: usize,
+    diagnostics: &mut Vec<MError>,
+) -> Stylesheet<'static> {
+    let rule = &input[range.clone()];
+    // the tree of the rule with the invalid parts removed can't borrow from the input
+    let mut source = rule.to_string();
+    // offsets and lengths of the removed parts, to find the errors in the input
+    let mut removed: Vec<(usize, usize)> = vec![];
+    // open constructs are closed once, then every iteration removes a part of the source
+    let mut closed = false;
+    loop {
+        let entity = match parse_css(&source) {
+            Ok((_, mut entities)) => {
+                entities.relocate(&|remaining| {
+                    range.start
+                        + original_offset(&removed, source.len() - remaining).min(rule.len())
+                });
+                return entities.into_owned();
+            }
+            Err(e) => failed_entity(&source, &e),
+        };
+        let error = MError::at_entity(&source, entity);
+        let offset = error.offset;
+        // empty declarations are allowed, they are just dropped
+        if error.expected.starts_with("declaration") && source[offset..].starts_with(';') {
+            removed.push((offset, 1));
+            source.remove(offset);
+            continue;
+        }
+        let original = range.start + original_offset(&removed, offset).min(rule.len());
+        let expected_declaration = error.expected.starts_with("declaration");
+        // the error stays at the same position while its rule is closed and skipped
+        if diagnostics.last().map(|e| e.offset) != Some(original) {
+            diagnostics.push(error.locate_from(input, original, (range.start, line)));
+        }
+
+        let declaration = match expected_declaration {
+            true => skip_declaration(&source[offset..]),
+            false => 0,
+        };
+        if offset + declaration == source.len() && !closed {
+            let closing = close_at_eof(&source[entity..]);
+            if !closing.is_empty() {
+                source.push_str(&closing);
+                closed = true;
+                continue;
+            }
+        }
+        let (start, len) = match declaration {
+            0 => (entity, skip_rule(&source[entity..])),
+            len => (offset, len),
+        };
+        if len == 0 {
+            return Default::default();
+        }
+        removed.push((start, len));
+        source.replace_range(start..start + len, "");
+    }
+}
+
+/// Offset in the input of the position in the source with the `removed` parts cut out
+fn original_offset(removed: &[(usize, usize)], offset: usize) -> usize {
+    removed.iter().rev().fold(offset, |offset, &(start, len)| {
+        if offset >= start {
+            offset + len
+        } else {
+            offset
+        }
+    })
+}
@@ -0,0 +1,392 @@
+use crate::optimizations::duplicates::RemoveDuplicates;
+use crate::optimizations::font::FontTransformer;
+use crate::optimizations::merge_blocks::MergeBlocks;
+use crate::optimizations::merge_container::MergeContainer;
+use crate::optimizations::merge_m_n_p::Merge;
+use crate::optimizations::merge_media::MergeMedia;
+use crate::optimizations::merge_rules::MergeRules;
+use crate::optimizations::merge_shorthand::MergeShortHand;
+use crate::optimizations::nesting::FlattenNesting;
+use crate::optimizations::selectors::{
+    RemoveDuplicateSelectors, SelectorTransformer, SortSelectors,
+};
+use crate::optimizations::transformer::{Transform, Transformer, TransformerParameterFn};
+use crate::optimizations::values::minify_value;
+use crate::optimizations::{lowercase, Level, Minifier};
+
+/// Built-in optimization passes in the order they are applied
+#[derive(Ord, PartialOrd, Eq, PartialEq, Debug, Copy, Clone, Hash)]
+pub enum Pass {
+    /// Rewrite nested rules into plain selectors, disabled by default
+    FlattenNesting,
+    /// Merge css blocks with equal selectors, Level Three
+    MergeBlocks,
+    /// Merge @media with equal screen, Level Three
+    MergeMedia,
+    /// Merge @container with equal condition, Level Three
+    MergeContainer,
+    /// Merge margins and paddings into shortcuts, Level Two
+    MergeMarginsPaddings,
+    /// Merge fonts, backgrounds, borders and others into shortcuts, Level Two
+    MergeShorthand,
+    /// Lowercase names, shorten colors and numbers, Level One
+    Values,
+    /// Replace font weights with numbers, Level One
+    Font,
+    /// Remove overridden declarations, Level One
+    RemoveDuplicates,
+    /// Remove whitespaces and quotes from selectors, Level One
+    Selectors,
+    /// Sort selectors of every selector list, disabled by default
+    SortSelectors,
+    /// Remove repeated selectors from selector lists, Level One
+    RemoveDuplicateSelectors,
+    /// Merge css blocks with equal declarations into one selector list, Level Three
+    MergeRules,
+}
+
+impl Pass {
+    const ALL: [Pass; 13] = [
+        Pass::FlattenNesting,
+        Pass::MergeBlocks,
+        Pass::MergeMedia,
+        Pass::MergeContainer,
+        Pass::MergeMarginsPaddings,
+        Pass::MergeShorthand,
+        Pass::Values,
+        Pass::Font,
+        Pass::RemoveDuplicates,
+        Pass::Selectors,
+        Pass::SortSelectors,
+        Pass::RemoveDuplicateSelectors,
+        Pass::MergeRules,
+    ];
+
+    /// Minimal level of the preset which enables the pass
+    pub fn level(self) -> Option<Level> {
+        match self {
+            Pass::FlattenNesting | Pass::SortSelectors => None,
+            Pass::MergeBlocks | Pass::MergeMedia | Pass::MergeContainer | Pass::MergeRules => {
+                Some(Level::Three)
+            }
+            Pass::MergeMarginsPaddings | Pass::MergeShorthand => Some(Level::Two),
+            Pass::Values
+            | Pass::Font
+            | Pass::RemoveDuplicates
+            | Pass::Selectors
+            | Pass::RemoveDuplicateSelectors => Some(Level::One),
+        }
+    }
+
+    /// Pass which moves declarations or rules between top level rules
+    pub(crate) fn is_cross_rule(self) -> bool {
+        matches!(
+            self,
+            Pass::MergeBlocks | Pass::MergeMedia | Pass::MergeContainer | Pass::MergeRules
+        )
+    }
+
+    /// Pass which changes declarations of every rule on its own
+    pub(crate) fn is_per_declaration(self) -> bool {
+        matches!(
+            self,
+            Pass::MergeMarginsPaddings | Pass::MergeShorthand | Pass::Values | Pass::Font
+        )
+    }
+
+    fn transform(self) -> BoxedTransform {
+        match self {
+            Pass::FlattenNesting => Box::new(FlattenNesting),
+            Pass::SortSelectors => Box::new(SortSelectors),
+            Pass::MergeBlocks => Box::new(MergeBlocks),
+            Pass::MergeMedia => Box::new(MergeMedia),
+            Pass::MergeContainer => Box::new(MergeContainer),
+            Pass::MergeMarginsPaddings => Box::new(Merge),
+            Pass::MergeShorthand => Box::new(MergeShortHand),
+            Pass::Values => Box::new(value_transformer()),
+            Pass::Font => Box::new(FontTransformer),
+            Pass::RemoveDuplicates => Box::new(RemoveDuplicates),
+            Pass::Selectors => Box::new(SelectorTransformer),
+            Pass::RemoveDuplicateSelectors => Box::new(RemoveDuplicateSelectors),
+            Pass::MergeRules => Box::new(MergeRules),
+        }
+    }
+}
+
+pub(crate) type BoxedTransform = Box<dyn Transform + Send + Sync>;
+
+/// Transform applied when the minification level is at least `level`
+pub(crate) struct Stage {
+    pub(crate) pass: Option<Pass>,
+    pub(crate) level: Level,
+    pub(crate) transform: BoxedTransform,
+}
+
+impl Stage {
+    pub(crate) fn is_per_declaration(&self) -> bool {
+        self.pass.is_some_and(Pass::is_per_declaration)
+    }
+}
+
+/// Builder of the [`Minifier`] pipeline. It starts from the level presets:
+/// every built-in pass runs from the level which enables it
+///
+/// ```rust
+/// use css_minify::optimizations::{Level, MinifierBuilder, Pass};
+/// let minifier = MinifierBuilder::default()
+///     .disable(Pass::MergeMedia)
+///     .enable(Pass::MergeBlocks, Level::One)
+///     .build();
+/// assert_eq!(
+///     minifier.minify("a { color: red } @media print { a { color: red } } a { margin: 0 }", Level::One),
+///     Ok("a{color:red;margin:0}@media print{a{color:red}}".into())
+/// );
+/// ```
+pub struct MinifierBuilder {
+    stages: Vec<Stage>,
+    threads: usize,
+}
+
+impl MinifierBuilder {
+    /// Builder without any passes
+    pub fn empty() -> Self {
+        MinifierBuilder {
+            stages: vec![],
+            threads: 1,
+        }
+    }
+
+    /// Run the built-in pass from the given level, it keeps its place in the pipeline
+    pub fn enable(mut self, pass: Pass, level: Level) -> Self {
+        match self.stages.iter_mut().find(|s| s.pass == Some(pass)) {
+            Some(stage) => stage.level = level,
+            None => {
+                let index = self.position(pass);
+                self.stages.insert(
+                    index,
+                    Stage {
+                        pass: Some(pass),
+                        level,
+                        transform: pass.transform(),
+                    },
+                );
+            }
+        }
+        self
+    }
+
+    pub fn disable(mut self, pass: Pass) -> Self {
+        self.stages.retain(|s| s.pass != Some(pass));
+        self
+    }
+
+    /// Run the transform from the given level right before the built-in pass,
+    /// or where the pass would be placed when it is disabled
+    pub fn insert_before(
+        mut self,
+        pass: Pass,
+        level: Level,
+        transform: impl Transform + Send + Sync + 'static,
+    ) -> Self {
+        let index = self
+            .stages
+            .iter()
+            .position(|s| s.pass == Some(pass))
+            .unwrap_or_else(|| self.position(pass));
+        self.insert(index, level, transform);
+        self
+    }
+
+    /// Run the transform from the given level right after the built-in pass,
+    /// or where the pass would be placed when it is disabled
+    pub fn insert_after(
+        mut self,
+        pass: Pass,
+        level: Level,
+        transform: impl Transform + Send + Sync + 'static,
+    ) -> Self {
+        let index = self
+            .stages
+            .iter()
+            .position(|s| s.pass == Some(pass))
+            .map(|index| index + 1)
+            .unwrap_or_else(|| self.position(pass));
+        self.insert(index, level, transform);
+        self
+    }
+
+    /// Run the transform from the given level after all other passes
+    pub fn push(mut self, level: Level, transform: impl Transform + Send + Sync + 'static) -> Self {
+        let index = self.stages.len();
+        self.insert(index, level, transform);
+        self
+    }
+
+    /// Transform top level rules of a stylesheet on the given number of threads,
+    /// only the passes which change declarations in place run in parallel
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn build(self) -> Minifier {
+        Minifier {
+            stages: self.stages,
+            threads: self.threads,
+        }
+    }
+
+    fn insert(
+        &mut self,
+        index: usize,
+        level: Level,
+        transform: impl Transform + Send + Sync + 'static,
+    ) {
+        self.stages.insert(
+            index,
+            Stage {
+                pass: None,
+                level,
+                transform: Box::new(transform),
+            },
+        );
+    }
+
+    /// Index before the first built-in pass which goes after the given one
+    fn position(&self, pass: Pass) -> usize {
+        self.stages
+            .iter()
+            .position(|s| s.pass.is_some_and(|p| p > pass))
+            .unwrap_or(self.stages.len())
+    }
+}
+
+impl Default for MinifierBuilder {
+    fn default() -> Self {
+        Pass::ALL
+            .into_iter()
+            .fold(MinifierBuilder::empty(), |builder, pass| {
+                match pass.level() {
+                    Some(level) => builder.enable(pass, level),
+                    None => builder,
+                }
+            })
+    }
+}
+
+impl From<Minifier> for MinifierBuilder {
+    fn from(minifier: Minifier) -> Self {
+        MinifierBuilder {
+            stages: minifier.stages,
+            threads: minifier.threads,
+        }
+    }
+}
+
+fn value_transformer() -> Transformer {
+    let mut transformer = Transformer::default();
+    transformer.register_parameter(TransformerParameterFn::Declaration(Box::new(
+        |mut parameter, _| {
+            parameter.value = minify_value(&parameter.name, parameter.value);
+            vec![parameter]
+        },
+    )));
+
+    // names of custom properties are case-sensitive
+    transformer.register_parameter(TransformerParameterFn::Declaration(Box::new(
+        |mut parameter, _| {
+            if !is_custom_property(&parameter.name) {
+                parameter.name = lowercase(parameter.name);
+            }
+            vec![parameter]
+        },
+    )));
+    transformer
+}
+
+fn is_custom_property(name: &str) -> bool {
+    name.starts_with("--")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::optimizations::transformer::Transform;
+    use crate::optimizations::{Level, MinifierBuilder, Pass};
+    use crate::structure::Parameters;
+
+    struct Uppercase;
+
+    impl Transform for Uppercase {
+        fn transform_parameters<'a>(&self, parameters: Parameters<'a>) -> Parameters<'a> {
+            parameters
+                .0
+                .into_iter()
+                .map(|mut p| {
+                    p.value = p.value.to_uppercase().into();
+                    p
+                })
+                .collect::<Vec<_>>()
+                .into()
+        }
+    }
+
+    #[test]
+    fn test_builder_passes() {
+        let input = "A { Color: #FFFFFF; margin-top: 0; margin-right: 0; margin-bottom: 0; margin-left: 0 }";
+        let minifier = MinifierBuilder::default()
+            .disable(Pass::Values)
+            .enable(Pass::MergeMarginsPaddings, Level::One)
+            .build();
+        assert_eq!(
+            minifier.minify(input, Level::One),
+            Ok("a{Color:#FFFFFF;margin:0}".into())
+        );
+        assert_eq!(
+            MinifierBuilder::empty().build().minify(input, Level::Three),
+            Ok("A{Color:#FFFFFF;margin-top:0;margin-right:0;margin-bottom:0;margin-left:0}".into())
+        );
+    }
+
+    #[test]
+    fn test_values_context() {
+        assert_eq!(
+            MinifierBuilder::default().build().minify(
+                "A { --Gap: 0px; Margin: var(--Gap) 0px; content: \"a 0.5, b\" }",
+                Level::One
+            ),
+            Ok("a{--Gap:0px;margin:var(--Gap) 0;content:\"a 0.5, b\"}".into())
+        );
+    }
+
+    #[test]
+    fn test_builder_custom() {
+        let input = "a { color: #ffffff }";
+        let before = MinifierBuilder::default()
+            .insert_before(Pass::Values, Level::One, Uppercase)
+            .build();
+        assert_eq!(before.minify(input, Level::One), Ok("a{color:#fff}".into()));
+        assert_eq!(
+            before.minify(input, Level::Zero),
+            Ok("a{color:#ffffff}".into())
+        );
+
+        let after = MinifierBuilder::default()
+            .insert_after(Pass::Values, Level::Zero, Uppercase)
+            .build();
+        assert_eq!(after.minify(input, Level::One), Ok("a{color:#FFF}".into()));
+        assert_eq!(
+            after.minify(input, Level::Zero),
+            Ok("a{color:#FFFFFF}".into())
+        );
+
+        let disabled = MinifierBuilder::default()
+            .disable(Pass::Values)
+            .insert_after(Pass::Values, Level::One, Uppercase)
+            .enable(Pass::Values, Level::One)
+            .build();
+        assert_eq!(
+            disabled.minify(input, Level::One),
+            Ok("a{color:#fff}".into())
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use crate::parsers::selector::LEGACY_PSEUDO_ELEMENTS;
+use crate::structure::{
+    ComplexSelector, CssEntity, PseudoClass, PseudoElement, Selector, Selectors,
+};
+
+/// Properties which are set together with other ones, the key is the first part of the name
+const FAMILY_ALIASES: [(&str, &str); 19] = [
+    // `inset` sets the physical offsets
+    ("top", "inset"),
+    ("right", "inset"),
+    ("bottom", "inset"),
+    ("left", "inset"),
+    // `gap` sets `row-gap` and `column-gap`, `grid-gap` is its legacy alias
+    ("row", "gap"),
+    ("column", "gap"),
+    ("columns", "gap"),
+    ("grid", "gap"),
+    // `place-*` sets `align-*` and `justify-*`
+    ("place", "align"),
+    ("justify", "align"),
+    // `font` resets `line-height`
+    ("line", "font"),
+    // `word-wrap` is the alias of `overflow-wrap`
+    ("word", "overflow"),
+    ("page", "break"),
+    // logical sizes are the physical ones depending on the writing mode
+    ("inline", "size"),
+    ("block", "size"),
+    ("width", "size"),
+    ("height", "size"),
+    ("min", "size"),
+    ("max", "size"),
+];
+
+/// Properties which outcome depends on the order of each other are in the same family:
+/// shorthands with their longhands, vendor prefixed aliases and logical properties
+pub(crate) fn property_family(name: &str) -> String {
+    if name.starts_with("--") {
+        return name.to_string();
+    }
+    let name = name.to_ascii_lowercase();
+    let unprefixed = match name.strip_prefix('-') {
+        Some(prefixed) => prefixed.split_once('-').map_or(prefixed, |(_, name)| name),
+        None => &name,
+    };
+    let family = unprefixed.split('-').next().unwrap_or(unprefixed);
+    FAMILY_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == family)
+        .map_or(family, |(_, family)| family)
+        .to_string()
+}
+
+/// `all` resets every property except the custom ones
+pub(crate) fn families_conflict(a: &str, b: &str) -> bool {
+    a == b || (a == "all" && !b.starts_with("--")) || (b == "all" && !a.starts_with("--"))
+}
+
+/// Declarations of the rules which are placed between two positions of the stylesheet,
+/// `None` selectors may match any element
+#[derive(Default)]
+pub(crate) struct Declared<'s, 'a> {
+    declarations: Vec<(Option<&'s Selectors<'a>>, String)>,
+}
+
+impl<'s, 'a> Declared<'s, 'a> {
+    /// Declarations of the rule and of the rules nested into it
+    pub(crate) fn add(&mut self, entity: &'s CssEntity<'a>) {
+        self.add_entity(entity, false)
+    }
+
+    /// Only declarations of the rules nested into the rule, its own ones are before the position
+    pub(crate) fn add_nested(&mut self, entity: &'s CssEntity<'a>) {
+        if let CssEntity::Block(block) = entity {
+            block.entities.iter().for_each(|e| self.add_entity(e, true));
+        }
+    }
+
+    /// Some rule may set the property for elements matched by the selectors
+    pub(crate) fn conflicts(&self, selectors: &Selectors, family: &str) -> bool {
+        self.declarations.iter().any(|(declared, declared_family)| {
+            families_conflict(declared_family, family)
+                && declared.is_none_or(|declared| may_overlap(declared, selectors))
+        })
+    }
+
+    /// Selectors of the nested rules are relative to their parents, so they may match anything.
+    /// Rules of the layers are skipped: their precedence doesn't depend on the position
+    fn add_entity(&mut self, entity: &'s CssEntity<'a>, nested: bool) {
+        let entities = match entity {
+            CssEntity::Block(block) => {
+                let selectors = (!nested).then_some(&block.selectors);
+                self.declarations.extend(
+                    block
+                        .parameters
+                        .iter()
+                        .map(|p| (selectors, property_family(&p.name))),
+                );
+                block.entities.iter().for_each(|e| self.add_entity(e, true));
+                return;
+            }
+            CssEntity::Media(media) => &media.entities,
+            CssEntity::Container(container) => &container.entities,
+            CssEntity::Supports(supports) => &supports.entities,
+            _ => return,
+        };
+        entities.iter().for_each(|e| self.add_entity(e, nested));
+    }
+}
+
+/// Selector lists may match the same element, unless the last compounds of every pair of
+/// selectors have different tags, ids or pseudo elements. The empty list of the bare block
+/// with declarations of the parent matches the parent, so it may overlap anything
+pub(crate) fn may_overlap(a: &Selectors, b: &Selectors) -> bool {
+    a.is_empty()
+        || b.is_empty()
+        || a.iter()
+            .any(|a| b.iter().any(|b| complex_may_overlap(a, b)))
+}
+
+fn complex_may_overlap(a: &ComplexSelector, b: &ComplexSelector) -> bool {
+    let (Some((_, a)), Some((_, b))) = (a.last(), b.last()) else {
+        return true;
+    };
+    let tag = |compound: &[Selector]| {
+        compound.iter().find_map(|s| match s {
+            Selector::Tag(tag) if !tag.contains(['*', '|']) => Some(tag.to_ascii_lowercase()),
+            _ => None,
+        })
+    };
+    let id = |compound: &[Selector]| {
+        compound.iter().find_map(|s| match s {
+            Selector::Id(id) => Some(id.to_string()),
+            _ => None,
+        })
+    };
+    // `:before` is the legacy syntax of `::before`
+    let pseudo_elements = |compound: &[Selector]| {
+        compound
+            .iter()
+            .filter_map(|s| match s {
+                Selector::PseudoElement(PseudoElement { name, argument, .. }) => Some((
+                    name.to_ascii_lowercase(),
+                    argument.as_ref().map(|a| a.to_string()),
+                )),
+                Selector::PseudoClass(PseudoClass {
+                    name,
+                    argument: None,
+                }) if LEGACY_PSEUDO_ELEMENTS.contains(&name.to_ascii_lowercase().as_str()) => {
+                    Some((name.to_ascii_lowercase(), None))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    let differ =
+        |a: Option<String>, b: Option<String>| matches!((a, b), (Some(a), Some(b)) if a != b);
+    !(differ(tag(a), tag(b)) || differ(id(a), id(b)) || pseudo_elements(a) != pseudo_elements(b))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::optimizations::cascade::{may_overlap, property_family};
+    use crate::parse;
+    use crate::structure::{CssEntity, Selectors};
+
+    fn selectors(input: &str) -> Selectors<'_> {
+        match parse(input).unwrap().0.remove(0) {
+            CssEntity::Block(block) => block.selectors,
+            entity => panic!("unexpected {:?}", entity),
+        }
+    }
+
+    #[test]
+    fn test_property_family() {
+        assert_eq!(property_family("margin-top"), "margin");
+        assert_eq!(property_family("-webkit-Transition"), "transition");
+        assert_eq!(property_family("inset-inline"), "inset");
+        assert_eq!(property_family("left"), "inset");
+        assert_eq!(property_family("min-inline-size"), "size");
+        assert_eq!(property_family("--Main-color"), "--Main-color");
+    }
+
+    #[test]
+    fn test_may_overlap() {
+        let overlap = |a, b| may_overlap(&selectors(a), &selectors(b));
+        assert!(overlap(".a{}", ".b{}"));
+        assert!(overlap("div.a{}", "span, .b{}"));
+        assert!(overlap("#x > p{}", "P{}"));
+        assert!(!overlap("div.a{}", "span.b{}"));
+        assert!(!overlap("#x{}", "#y{}"));
+        assert!(!overlap(".a::before{}", ".a::after{}"));
+        assert!(!overlap(".a{}", ".a:before{}"));
+        assert!(may_overlap(&Selectors::default(), &selectors("p{}")));
+    }
+}
@@ -1,84 +1,476 @@
-use crate::parsers::utils::non_useless;
-use derive_more::{Deref, DerefMut, From, Into};
-use nom::branch::alt;
-use nom::bytes::complete::tag;
-use nom::character::complete::{char, digit1, hex_digit1};
-use nom::combinator::{map, recognize};
-use nom::sequence::{preceded, terminated, tuple};
-use nom::IResult;
-
-#[derive(Clone, Eq, PartialEq, Default, Debug, Deref, DerefMut, From, Into)]
-pub struct Color(String);
-
-pub fn optimize_color(input: &str) -> Color {
-    alt((parse_hex, parse_rgb))(input)
-        .map(|(postfix, mut color)| {
-            if postfix.trim() == "!important" {
-                color.0 += "!important"
-            }
-            color
-        })
-        .unwrap_or_else(|_| Color(input.into()))
+use crate::optimizations::numbers::minify_number;
+use crate::structure::{ComponentValue, ComponentValues};
+
+/// Named colors of css, the shortest name is chosen when several have the same color
+const NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+/// Unprefixed properties which take colors
+const COLOR_PROPERTIES: [&str; 35] = [
+    "color",
+    "background",
+    "background-color",
+    "border",
+    "border-color",
+    "border-top",
+    "border-right",
+    "border-bottom",
+    "border-left",
+    "border-top-color",
+    "border-right-color",
+    "border-bottom-color",
+    "border-left-color",
+    "border-block",
+    "border-block-color",
+    "border-inline",
+    "border-inline-color",
+    "outline",
+    "outline-color",
+    "box-shadow",
+    "text-shadow",
+    "fill",
+    "stroke",
+    "caret-color",
+    "accent-color",
+    "column-rule",
+    "column-rule-color",
+    "text-decoration",
+    "text-decoration-color",
+    "text-emphasis",
+    "text-emphasis-color",
+    "scrollbar-color",
+    "stop-color",
+    "flood-color",
+    "lighting-color",
+];
+
+/// Color with 8 bit channels and the alpha from 0 to 1
+#[derive(Clone, PartialEq, Debug)]
+struct Rgba {
+    red: u8,
+    green: u8,
+    blue: u8,
+    alpha: f64,
 }
 
-pub fn parse_hex(input: &str) -> IResult<&str, Color> {
-    map(recognize(preceded(tag("#"), hex_digit1)), |color: &str| {
-        if color.len() > 3 && color[1..4] == color[4..] {
-            return Color(color[..4].to_lowercase());
+impl Rgba {
+    fn from_hex(hex: &str) -> Option<Self> {
+        let digits = hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<Vec<u8>>>()?;
+        let channels = match digits.len() {
+            3 | 4 => digits.iter().map(|d| d * 17).collect::<Vec<_>>(),
+            6 | 8 => digits.chunks(2).map(|d| d[0] * 16 + d[1]).collect(),
+            _ => return None,
+        };
+        Some(Rgba {
+            red: channels[0],
+            green: channels[1],
+            blue: channels[2],
+            alpha: channels.get(3).map_or(1.0, |a| *a as f64 / 255.0),
+        })
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        if name == "transparent" {
+            return Some(Rgba {
+                red: 0,
+                green: 0,
+                blue: 0,
+                alpha: 0.0,
+            });
         }
-        Color(color.to_lowercase())
-    })(input)
-}
+        let (_, color) = NAMED_COLORS.iter().find(|(n, _)| *n == name)?;
+        Some(Rgba {
+            red: (color >> 16) as u8,
+            green: (color >> 8) as u8,
+            blue: *color as u8,
+            alpha: 1.0,
+        })
+    }
 
-pub fn parse_rgb(input: &str) -> IResult<&str, Color> {
-    terminated(
-        preceded(
-            terminated(non_useless(tag("rgb")), char('(')),
-            map(
-                tuple((
-                    terminated(non_useless(digit1), char(',')),
-                    terminated(non_useless(digit1), char(',')),
-                    non_useless(digit1),
-                )),
-                |(red, green, blue): (&str, &str, &str)| {
-                    let color = format!(
-                        "#{:02X}{:02X}{:02X}",
-                        str::parse::<u8>(red).unwrap(),
-                        str::parse::<u8>(green).unwrap(),
-                        str::parse::<u8>(blue).unwrap(),
-                    );
-
-                    if color[1..4] == color[4..] {
-                        return Color(color[..4].to_lowercase());
-                    }
-                    Color(color.to_lowercase())
-                },
+    /// `rgb()`, `rgba()`, `hsl()` and `hsla()` with numbers only,
+    /// channels out of range are clamped like browsers do
+    fn from_function(name: &str, arguments: &ComponentValues) -> Option<Self> {
+        let arguments = color_arguments(arguments)?;
+        let alpha = match arguments.get(3) {
+            Some(alpha) => fraction(alpha, 1.0)?,
+            None => 1.0,
+        };
+        let [red, green, blue] = match name.to_ascii_lowercase().as_str() {
+            "rgb" | "rgba" => [
+                fraction(arguments[0], 255.0)?,
+                fraction(arguments[1], 255.0)?,
+                fraction(arguments[2], 255.0)?,
+            ],
+            "hsl" | "hsla" => hsl_to_rgb(
+                hue(arguments[0])?,
+                percentage(arguments[1])?,
+                percentage(arguments[2])?,
             ),
-        ),
-        tag(")"),
-    )(input)
+            _ => return None,
+        };
+        Some(Rgba {
+            red: (red * 255.0).round() as u8,
+            green: (green * 255.0).round() as u8,
+            blue: (blue * 255.0).round() as u8,
+            alpha,
+        })
+    }
+
+    /// Shortest way to write the color. Colors with the alpha are written as `rgba()`
+    /// or `transparent` which old browsers read, 4 and 8 digit hex only when `alpha_hex` is set
+    fn to_component_value(&self, alpha_hex: bool) -> ComponentValue {
+        let byte_alpha = (self.alpha * 255.0).round();
+        let mut candidates = vec![];
+        if self.alpha == 1.0 || alpha_hex && (self.alpha * 255.0 - byte_alpha).abs() < 1e-6 {
+            let mut channels = vec![self.red, self.green, self.blue];
+            if byte_alpha != 255.0 {
+                channels.push(byte_alpha as u8);
+            }
+            let short = channels.iter().all(|c| c % 17 == 0);
+            let hex = channels
+                .iter()
+                .map(|c| match short {
+                    true => format!("{:x}", c / 17),
+                    false => format!("{:02x}", c),
+                })
+                .collect::<String>();
+            candidates.push(ComponentValue::Hash(hex));
+        }
+        if self.alpha != 1.0 {
+            let alpha = minify_number(&format!("{}", self.alpha));
+            let arguments = [self.red, self.green, self.blue]
+                .iter()
+                .map(|c| ComponentValue::Number(c.to_string()))
+                .chain([ComponentValue::Number(alpha)])
+                .flat_map(|c| [ComponentValue::Comma, c])
+                .skip(1)
+                .collect::<Vec<_>>();
+            candidates.push(ComponentValue::Function(
+                "rgba".into(),
+                ComponentValues(arguments),
+            ));
+        }
+        if self.alpha == 0.0 && [self.red, self.green, self.blue] == [0, 0, 0] {
+            candidates.push(ComponentValue::Ident("transparent".into()));
+        }
+        if self.alpha == 1.0 {
+            let color = (self.red as u32) << 16 | (self.green as u32) << 8 | self.blue as u32;
+            candidates.extend(
+                NAMED_COLORS
+                    .iter()
+                    .filter(|(_, c)| *c == color)
+                    .map(|(name, _)| ComponentValue::Ident(name.to_string())),
+            );
+        }
+        candidates
+            .into_iter()
+            .min_by_key(|c| c.to_string().len())
+            .unwrap()
+    }
+}
+
+/// Shortest way to write the color token, `None` when it is not a color.
+/// Keywords are colors only when `keywords` is set
+pub(crate) fn minify_color(value: &ComponentValue, keywords: bool) -> Option<ComponentValue> {
+    let color = match value {
+        ComponentValue::Hash(hex) => Rgba::from_hex(hex)?,
+        ComponentValue::Ident(name) if keywords => Rgba::from_name(name)?,
+        ComponentValue::Function(name, arguments) => Rgba::from_function(name, arguments)?,
+        _ => return None,
+    };
+    let alpha_hex = matches!(value, ComponentValue::Hash(hex) if matches!(hex.len(), 4 | 8));
+    let minified = color.to_component_value(alpha_hex);
+    match (value, minified.to_string().len() < value.to_string().len()) {
+        (_, true) => Some(minified),
+        (ComponentValue::Hash(hex), false) => Some(ComponentValue::Hash(hex.to_lowercase())),
+        _ => None,
+    }
+}
+
+/// Property which takes colors, elsewhere `white` may be a name of an animation
+/// and `#fff` a part of an unknown syntax
+pub(crate) fn is_color_property(name: &str) -> bool {
+    COLOR_PROPERTIES.contains(&name)
+}
+
+/// Unprefixed function which takes colors in any property: gradients and `drop-shadow()`
+pub(crate) fn is_color_function(name: &str) -> bool {
+    name.contains("gradient") || name == "drop-shadow"
+}
+
+/// Three channels and the optional alpha of both comma and space separated syntaxes
+fn color_arguments(arguments: &ComponentValues) -> Option<Vec<&ComponentValue>> {
+    let tokens = arguments
+        .iter()
+        .filter(|v| !matches!(v, ComponentValue::Whitespace))
+        .collect::<Vec<_>>();
+    let separated = match tokens.iter().any(|v| matches!(v, ComponentValue::Comma)) {
+        true => tokens
+            .chunks(2)
+            .map(|chunk| match chunk {
+                [value] | [value, ComponentValue::Comma] => Some(*value),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .filter(|_| !matches!(tokens.last(), Some(ComponentValue::Comma)))?,
+        false => match tokens.as_slice() {
+            [red, green, blue] => vec![*red, *green, *blue],
+            [red, green, blue, ComponentValue::Delim('/'), alpha] => {
+                vec![*red, *green, *blue, *alpha]
+            }
+            _ => return None,
+        },
+    };
+    match separated.len() {
+        3 | 4 => Some(separated),
+        _ => None,
+    }
+}
+
+/// Number or percentage as a fraction of `max` clamped to `0..=1`
+fn fraction(value: &ComponentValue, max: f64) -> Option<f64> {
+    let fraction = match value {
+        ComponentValue::Number(number) => number.parse::<f64>().ok()? / max,
+        ComponentValue::Percentage(number) => number.parse::<f64>().ok()? / 100.0,
+        _ => return None,
+    };
+    fraction.is_finite().then(|| fraction.clamp(0.0, 1.0))
+}
+
+/// Saturation or lightness as a fraction clamped to `0..=1`
+fn percentage(value: &ComponentValue) -> Option<f64> {
+    let (ComponentValue::Percentage(number) | ComponentValue::Number(number)) = value else {
+        return None;
+    };
+    let fraction = number.parse::<f64>().ok()? / 100.0;
+    fraction.is_finite().then(|| fraction.clamp(0.0, 1.0))
+}
+
+/// Hue in degrees
+fn hue(value: &ComponentValue) -> Option<f64> {
+    let (number, unit) = match value {
+        ComponentValue::Number(number) => (number, "deg".to_string()),
+        ComponentValue::Dimension(number, unit) => (number, unit.to_ascii_lowercase()),
+        _ => return None,
+    };
+    let number = number.parse::<f64>().ok()?;
+    let degrees = match unit.as_str() {
+        "deg" => number,
+        "grad" => number * 0.9,
+        "rad" => number.to_degrees(),
+        "turn" => number * 360.0,
+        _ => return None,
+    };
+    degrees.is_finite().then_some(degrees)
+}
+
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> [f64; 3] {
+    let hue = hue.rem_euclid(360.0) / 30.0;
+    let chroma = saturation * lightness.min(1.0 - lightness);
+    [0.0, 8.0, 4.0].map(|offset| {
+        let k = (offset + hue) % 12.0;
+        lightness - chroma * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    })
 }
 
 #[cfg(test)]
 mod test {
-    use crate::optimizations::color::{parse_hex, parse_rgb, Color};
+    use crate::optimizations::color::minify_color;
+    use crate::structure::ComponentValues;
 
-    #[test]
-    fn test_rgb() {
-        assert_eq!(
-            parse_rgb("rgb(255, 255, /* lol */ 255 )"),
-            Ok(("", Color("#fff".into())))
-        )
+    fn minify(color: &str, keywords: bool) -> String {
+        let value = ComponentValues::parse(color).0.remove(0);
+        minify_color(&value, keywords).map_or(color.into(), |c| c.to_string())
     }
+
     #[test]
-    fn test_small_rgb() {
-        assert_eq!(
-            parse_rgb("rgb(4, 120, 87)"),
-            Ok(("", Color("#047857".into())))
-        )
+    fn test_color() {
+        for (value, expected) in [
+            ("rgb(255, 255, /* lol */ 255 )", "#fff"),
+            ("rgb(4, 120, 87)", "#047857"),
+            ("rgb(300, 0, -5)", "red"),
+            ("rgb(100%, 50%, 0%)", "#ff8000"),
+            ("rgb(255 0 0 / 50%)", "rgba(255,0,0,.5)"),
+            ("rgba(255, 0, 0, 1)", "red"),
+            ("rgba(0, 0, 0, 0)", "transparent"),
+            ("rgba(255, 0, 0, 0)", "rgba(255,0,0,0)"),
+            ("rgba(0, 0, 0, 0.5)", "rgba(0,0,0,.5)"),
+            ("rgba(0, 0, 0, 2)", "#000"),
+            ("hsl(120, 100%, 50%)", "#0f0"),
+            ("hsla(0.5turn 100% 25% / 1)", "teal"),
+            ("#FFF", "#fff"),
+            ("#FF0000", "red"),
+            ("#ff0000ff", "red"),
+            ("#11223344", "#1234"),
+            ("#12345678", "#12345678"),
+            ("WHITE", "#fff"),
+            ("transparent", "transparent"),
+            ("#0000", "#0000"),
+            ("#00000000", "#0000"),
+            ("aqua", "aqua"),
+            ("navy", "navy"),
+            ("rgb(var(--r), 0, 0)", "rgb(var(--r), 0, 0)"),
+            ("rgb(1, 2)", "rgb(1, 2)"),
+        ] {
+            assert_eq!(minify(value, true), expected);
+        }
     }
+
     #[test]
-    fn test_hex() {
-        assert_eq!(parse_hex("#fff"), Ok(("", Color("#fff".into()))))
+    fn test_color_keywords() {
+        assert_eq!(minify("white", false), "white");
+        assert_eq!(minify("#FFFFFF", false), "#fff");
     }
 }
@@ -0,0 +1,142 @@
+use crate::optimizations::transformer::Transform;
+use crate::structure::{Parameter, Parameters};
+
+/// Removes declarations overridden by a later declaration with the same name.
+/// Repeated declarations are usually fallbacks for older browsers
+/// (`display:-webkit-box;display:flex`), so the earlier one is only dropped
+/// when the values are equal or the later one may not be ignored by any browser.
+#[derive(Default, Debug, Clone)]
+pub struct RemoveDuplicates;
+
+impl Transform for RemoveDuplicates {
+    fn transform_parameters<'a>(&self, parameters: Parameters<'a>) -> Parameters<'a> {
+        let mut dead = vec![false; parameters.len()];
+        for i in 0..parameters.len() {
+            for j in i + 1..parameters.len() {
+                if dead[j] || !parameters[i].name.eq_ignore_ascii_case(&parameters[j].name) {
+                    continue;
+                }
+                if !equal_values(&parameters[i], &parameters[j]) {
+                    match (is_important(&parameters[i]), is_important(&parameters[j])) {
+                        (true, false) if is_always_valid(&parameters[i]) => dead[j] = true,
+                        (false, _) | (true, true) if is_always_valid(&parameters[j]) => {
+                            dead[i] = true;
+                            break;
+                        }
+                        _ => {}
+                    }
+                } else if is_important(&parameters[i]) && !is_important(&parameters[j]) {
+                    dead[j] = true;
+                } else {
+                    dead[i] = true;
+                    break;
+                }
+            }
+        }
+
+        let mut dead = dead.into_iter();
+        parameters
+            .0
+            .into_iter()
+            .filter(|_| !dead.next().unwrap_or_default())
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+fn is_important(parameter: &Parameter) -> bool {
+    parameter.value.ends_with("!important")
+}
+
+fn equal_values(first: &Parameter, second: &Parameter) -> bool {
+    first.value.trim_end_matches("!important").trim()
+        == second.value.trim_end_matches("!important").trim()
+}
+
+/// Custom properties are never validated and css-wide keywords are valid for every property,
+/// so they are always applied. Any other value may be unsupported by some browser
+fn is_always_valid(parameter: &Parameter) -> bool {
+    parameter.name.starts_with("--")
+        || matches!(
+            parameter
+                .value
+                .trim_end_matches("!important")
+                .trim()
+                .to_ascii_lowercase()
+                .as_str(),
+            "inherit" | "initial" | "unset"
+        )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::optimizations::duplicates::RemoveDuplicates;
+    use crate::optimizations::transformer::Transform;
+    use crate::structure::Parameters;
+
+    fn parameters<'a>(declarations: &[(&'a str, &'a str)]) -> Parameters<'a> {
+        declarations
+            .iter()
+            .map(|&(name, value)| (name.into(), value.into()))
+            .collect()
+    }
+
+    #[test]
+    fn test_keep_fallbacks() {
+        let fallbacks = parameters(&[
+            ("display", "-webkit-box"),
+            ("display", "flex"),
+            ("background", "#fff"),
+            ("background", "linear-gradient(#fff, #000)"),
+            ("color", "red"),
+            ("color", "10px"),
+            ("z-index", "1"),
+            ("z-index", "1.5"),
+            ("width", "auto"),
+            ("width", "-5px"),
+        ]);
+        assert_eq!(
+            RemoveDuplicates.transform_parameters(fallbacks.clone()),
+            fallbacks
+        )
+    }
+
+    #[test]
+    fn test_remove_dead() {
+        assert_eq!(
+            RemoveDuplicates.transform_parameters(parameters(&[
+                ("width", "calc(100% - 10px)"),
+                ("color", "red"),
+                ("width", "inherit"),
+                ("color", "red"),
+                ("--gap", "1px"),
+                ("--gap", "var(--x)"),
+            ])),
+            parameters(&[
+                ("width", "inherit"),
+                ("color", "red"),
+                ("--gap", "var(--x)")
+            ])
+        )
+    }
+
+    #[test]
+    fn test_important() {
+        assert_eq!(
+            RemoveDuplicates.transform_parameters(parameters(&[
+                ("color", "unset !important"),
+                ("color", "#000"),
+                ("z-index", "none !important"),
+                ("z-index", "1"),
+                ("margin", "1px"),
+                ("margin", "initial !important"),
+            ])),
+            parameters(&[
+                ("color", "unset !important"),
+                ("z-index", "none !important"),
+                ("z-index", "1"),
+                ("margin", "initial !important")
+            ])
+        )
+    }
+}
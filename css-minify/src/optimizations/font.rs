@@ -1,19 +1,18 @@
+use crate::optimizations::replace;
 use crate::optimizations::transformer::Transform;
-use crate::structure::{Parameters, Value};
+use crate::structure::{Parameter, Parameters};
 
 #[derive(Default, Debug, Clone)]
 pub(crate) struct FontTransformer;
 
 impl Transform for FontTransformer {
-    fn transform_parameters(&self, mut parameters: Parameters) -> Parameters {
+    fn transform_parameters<'a>(&self, mut parameters: Parameters<'a>) -> Parameters<'a> {
         parameters
             .iter_mut()
-            .filter(|(name, _)| matches!(name.as_str(), "font-weight"))
-            .for_each(|(_, value): (_, &mut Value)| {
-                *value = value
-                    .replace("normal", "400")
-                    .replace("bold", "700")
-                    .replace('\"', "");
+            .filter(|p| p.name == "font-weight")
+            .for_each(|Parameter { value, .. }| {
+                let weight = replace(std::mem::take(value), "normal", "400");
+                *value = replace(replace(weight, "bold", "700"), "\"", "");
             });
         parameters
     }
@@ -23,35 +22,31 @@ impl Transform for FontTransformer {
 mod test {
     use crate::optimizations::font::FontTransformer;
     use crate::optimizations::transformer::Transform;
-    use crate::structure::{Block, CssEntities, CssEntity, Selector, SelectorWithPseudoClasses};
+    use crate::structure::{Block, CssEntities, CssEntity, Selector};
     use indexmap::map::IndexMap;
 
     #[test]
     fn test_blocks() {
         assert_eq!(
             FontTransformer::default().transform_many(CssEntities(vec![CssEntity::Block(Block {
-                selectors: vec![SelectorWithPseudoClasses(
-                    Some(Selector::Class("test".into())),
-                    vec![],
-                )]
-                .into(),
+                selectors: vec![Selector::Class("test".into()).into()].into(),
                 parameters: {
                     let mut tmp = IndexMap::new();
                     tmp.insert("font-weight".into(), "bold".into());
                     tmp.into()
                 },
+                entities: Default::default(),
+                location: Default::default(),
             }),])),
             CssEntities(vec![CssEntity::Block(Block {
-                selectors: vec![SelectorWithPseudoClasses(
-                    Some(Selector::Class("test".into())),
-                    vec![],
-                )]
-                .into(),
+                selectors: vec![Selector::Class("test".into()).into()].into(),
                 parameters: {
                     let mut tmp = IndexMap::new();
                     tmp.insert("font-weight".into(), "700".into());
                     tmp.into()
                 },
+                entities: Default::default(),
+                location: Default::default(),
             }),])
         )
     }
@@ -1,116 +1,191 @@
+use crate::optimizations::cascade::{families_conflict, property_family, Declared};
+use crate::optimizations::selectors::sort_selectors;
 use crate::optimizations::transformer::Transform;
-use crate::structure::{Block, CssEntities, CssEntity, Media, Parameters, Supports};
-use indexmap::map::IndexMap;
+use crate::structure::{
+    Block, Container, CssEntities, CssEntity, Layer, Media, Parameter, Supports,
+};
+use std::collections::HashMap;
 
+/// Merges declarations of css blocks into the previous block with equal selectors.
+/// Declarations are moved only if no rule between the blocks may set the same property
+/// for the same elements, so the cascade outcome is kept
 #[derive(Default, Debug, Clone)]
 pub struct MergeBlocks;
 
 impl Transform for MergeBlocks {
-    fn transform_parameters(&self, parameters: Parameters) -> Parameters {
-        parameters
+    fn transform<'a>(&self, entity: CssEntity<'a>) -> CssEntity<'a> {
+        entity
     }
 
-    fn transform(&self, entity: CssEntity) -> CssEntity {
-        entity
+    fn transform_many<'a>(&self, entities: CssEntities<'a>) -> CssEntities<'a> {
+        let mut merged: Vec<CssEntity<'a>> = Vec::with_capacity(entities.len());
+        // last block with the selectors
+        let mut targets = HashMap::new();
+        for entity in entities.0 {
+            let mut block = match entity {
+                CssEntity::Block(mut block) => {
+                    block.entities = self.transform_many(block.entities);
+                    block
+                }
+                entity => {
+                    merged.push(self.transform_nested(entity));
+                    continue;
+                }
+            };
+            let key = sort_selectors(block.selectors.clone()).to_string();
+            if let Some(&target) = targets.get(&key) {
+                let moved = movable(&merged[target..], &mut block);
+                if let CssEntity::Block(target) = &mut merged[target] {
+                    target.parameters.0.extend(moved);
+                }
+                if block.parameters.is_empty() && block.entities.is_empty() {
+                    continue;
+                }
+            }
+            targets.insert(key, merged.len());
+            merged.push(block.into());
+        }
+        merged.into()
     }
+}
 
-    fn transform_many(&self, entities: CssEntities) -> CssEntities {
-        let mut blocks_to_merge = IndexMap::new();
-        entities
-            .0
-            .iter()
-            .filter_map(|e| match e {
-                CssEntity::Block(b) => Some(b),
-                _ => None,
-            })
-            .for_each(|b| {
-                blocks_to_merge
-                    .entry(b.selectors.clone().to_string())
-                    .and_modify(|block: &mut Block| {
-                        b.parameters.0.iter().for_each(|(n, v)| {
-                            block.parameters.0.insert(n.clone(), v.clone());
-                        });
-                    })
-                    .or_insert(b.clone());
-            });
-        let mut non_block_entities = CssEntities(
-            entities
-                .0
-                .into_iter()
-                .filter(|e| !matches!(e, CssEntity::Block(_)))
-                .map(|e| match e {
-                    CssEntity::Media(Media { screen, entities }) => Media {
-                        screen,
-                        entities: self.transform_many(entities),
-                    }
-                    .into(),
-                    CssEntity::Supports(Supports {
-                        conditions,
-                        entities,
-                    }) => Supports {
-                        conditions,
-                        entities: self.transform_many(entities),
-                    }
-                    .into(),
-                    entity => entity,
-                })
-                .collect(),
-        );
-        non_block_entities
-            .0
-            .append(&mut blocks_to_merge.into_iter().map(|(_, m)| m.into()).collect());
-        non_block_entities
+impl MergeBlocks {
+    /// Blocks are merged inside of conditional rules on their own
+    fn transform_nested<'a>(&self, entity: CssEntity<'a>) -> CssEntity<'a> {
+        match entity {
+            CssEntity::Media(Media { screen, entities }) => Media {
+                screen,
+                entities: self.transform_many(entities),
+            }
+            .into(),
+            CssEntity::Container(Container {
+                name,
+                condition,
+                entities,
+            }) => Container {
+                name,
+                condition,
+                entities: self.transform_many(entities),
+            }
+            .into(),
+            CssEntity::Supports(Supports {
+                conditions,
+                entities,
+            }) => Supports {
+                conditions,
+                entities: self.transform_many(entities),
+            }
+            .into(),
+            CssEntity::Layer(Layer { names, entities }) => Layer {
+                names,
+                entities: entities.map(|entities| self.transform_many(entities)),
+            }
+            .into(),
+            entity => entity,
+        }
     }
 }
 
+/// Takes declarations of the block which may be moved to the end of the first entity
+/// of `before`: the rules after it don't set them. Once a declaration stays,
+/// later declarations of the same property stay too
+fn movable<'a>(before: &[CssEntity<'a>], block: &mut Block<'a>) -> Vec<Parameter<'a>> {
+    let mut declared = Declared::default();
+    declared.add_nested(&before[0]);
+    before[1..].iter().for_each(|entity| declared.add(entity));
+    let mut stayed: Vec<String> = vec![];
+    let mut moved = vec![];
+    for parameter in std::mem::take(&mut block.parameters.0) {
+        let family = property_family(&parameter.name);
+        if stayed.iter().any(|s| families_conflict(s, &family))
+            || declared.conflicts(&block.selectors, &family)
+        {
+            stayed.push(family);
+            block.parameters.push(parameter);
+        } else {
+            moved.push(parameter);
+        }
+    }
+    moved
+}
+
 #[cfg(test)]
 mod test {
     use crate::optimizations::merge_blocks::MergeBlocks;
     use crate::optimizations::transformer::Transform;
-    use crate::structure::{Block, CssEntities, CssEntity, Selector, SelectorWithPseudoClasses};
+    use crate::parse;
+    use crate::structure::{Block, CssEntities, CssEntity, Selector};
     use indexmap::map::IndexMap;
 
+    fn merge(input: &str) -> String {
+        MergeBlocks
+            .transform_many(parse(input).unwrap())
+            .to_string()
+    }
+
+    #[test]
+    fn test_cascade() {
+        assert_eq!(
+            merge(".a{&{color:blue}color:green;&{color:yellow}}"),
+            ".a{&{color:blue}color:green;&{color:yellow}}"
+        );
+        assert_eq!(
+            merge(".a{color:red}.b{color:blue}.a{color:green;margin:0}"),
+            ".a{color:red;margin:0}.b{color:blue}.a{color:green}"
+        );
+        assert_eq!(
+            merge(".a{margin:0}.b{margin-top:1px}.a{margin-left:0;padding:0;margin:1px}"),
+            ".a{margin:0;padding:0}.b{margin-top:1px}.a{margin-left:0;margin:1px}"
+        );
+        assert_eq!(
+            merge("p{color:red}span{color:blue}p{color:green}#x{all:unset}p{--x:1}"),
+            "p{color:red;color:green;--x:1}span{color:blue}#x{all:unset}"
+        );
+        assert_eq!(
+            merge("a{top:0}@media print{.b{inset:0}}a{left:0;color:red}"),
+            "a{top:0;color:red}@media print{.b{inset:0}}a{left:0}"
+        );
+        assert_eq!(
+            merge(".a{color:red}.b{&.c{color:blue}}.a{color:green;-webkit-transition:none}"),
+            ".a{color:red;-webkit-transition:none}.b{&.c{color:blue}}.a{color:green}"
+        );
+    }
+
     #[test]
     fn test_blocks() {
         assert_eq!(
             MergeBlocks::default().transform_many(CssEntities(vec![
                 CssEntity::Block(Block {
-                    selectors: vec![SelectorWithPseudoClasses(
-                        Some(Selector::Class("test".into())),
-                        vec![]
-                    )]
-                    .into(),
+                    selectors: vec![Selector::Class("test".into()).into()].into(),
                     parameters: {
                         let mut tmp = IndexMap::new();
                         tmp.insert("background-color".into(), "#f64e60 !important".into());
                         tmp.into()
-                    }
+                    },
+                    entities: Default::default(),
+                    location: Default::default(),
                 }),
                 CssEntity::Block(Block {
-                    selectors: vec![SelectorWithPseudoClasses(
-                        Some(Selector::Class("test".into())),
-                        vec![]
-                    )]
-                    .into(),
+                    selectors: vec![Selector::Class("test".into()).into()].into(),
                     parameters: {
                         let mut tmp = IndexMap::new();
                         tmp.insert("color".into(), "#f64e60 !important".into());
                         tmp.into()
-                    }
+                    },
+                    entities: Default::default(),
+                    location: Default::default(),
                 }),
             ])),
             CssEntities(vec![CssEntity::Block(Block {
-                selectors: vec![SelectorWithPseudoClasses(
-                    Some(Selector::Class("test".into())),
-                    vec![]
-                )]
-                .into(),
+                selectors: vec![Selector::Class("test".into()).into()].into(),
                 parameters: {
                     let mut tmp = IndexMap::new();
                     tmp.insert("background-color".into(), "#f64e60 !important".into());
                     tmp.insert("color".into(), "#f64e60 !important".into());
                     tmp.into()
-                }
+                },
+                entities: Default::default(),
+                location: Default::default(),
             })])
         )
     }
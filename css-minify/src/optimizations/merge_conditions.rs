@@ -0,0 +1,106 @@
+use crate::optimizations::cascade::{property_family, Declared};
+use crate::optimizations::transformer::Transform;
+use crate::structure::{Container, CssEntities, CssEntity, Layer, Media, Supports};
+use std::collections::HashMap;
+
+/// Conditional at-rules which are merged when their conditions are equal
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Condition {
+    Media,
+    Container,
+}
+
+impl Condition {
+    /// Rules of the kind with equal keys are merged, whitespaces after colons don't matter
+    fn key(self, entity: &CssEntity) -> Option<String> {
+        match (self, entity) {
+            (Condition::Media, CssEntity::Media(media)) => Some(media.screen.replace(": ", ":")),
+            (Condition::Container, CssEntity::Container(container)) => Some(format!(
+                "{:?} {}",
+                container.name,
+                container.condition.replace(": ", ":")
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Merges the at-rule of the `condition` kind into the previous one with the equal condition,
+/// when no rule between them may set the same properties for the same elements.
+/// The rules nested into conditional rules and layers are merged on their own.
+/// Every at-rule of the kind is transformed by the `transform`, which minifies its condition
+pub(crate) fn merge_conditions<'a>(
+    transform: &impl Transform,
+    condition: Condition,
+    entities: CssEntities<'a>,
+) -> CssEntities<'a> {
+    let mut merged: Vec<CssEntity<'a>> = Vec::with_capacity(entities.len());
+    // last at-rule with the condition
+    let mut targets = HashMap::new();
+    for mut entity in entities.0 {
+        if let Some(entities) = rules_mut(&mut entity) {
+            *entities = transform.transform_many(std::mem::take(entities));
+        }
+        let Some(key) = condition.key(&entity) else {
+            merged.push(entity);
+            continue;
+        };
+        let entity = transform.transform(entity);
+        if let Some(&target) = targets.get(&key) {
+            if can_move(&merged[target + 1..], &entity) {
+                append(&mut merged[target], entity);
+                continue;
+            }
+        }
+        targets.insert(key, merged.len());
+        merged.push(entity);
+    }
+    merged.into()
+}
+
+/// Rules of the at-rule may be moved before the rules `between`
+fn can_move(between: &[CssEntity], entity: &CssEntity) -> bool {
+    let mut declared = Declared::default();
+    between.iter().for_each(|entity| declared.add(entity));
+    between.is_empty() || !conflicts(&declared, entity)
+}
+
+/// Nested rules are relative to their parents and other at-rules may depend on the order,
+/// so they are never moved
+fn conflicts(declared: &Declared, entity: &CssEntity) -> bool {
+    match entity {
+        CssEntity::Block(block) => {
+            !block.entities.is_empty()
+                || block
+                    .parameters
+                    .iter()
+                    .any(|p| declared.conflicts(&block.selectors, &property_family(&p.name)))
+        }
+        CssEntity::Media(Media { entities, .. })
+        | CssEntity::Container(Container { entities, .. })
+        | CssEntity::Supports(Supports { entities, .. }) => {
+            entities.iter().any(|entity| conflicts(declared, entity))
+        }
+        _ => true,
+    }
+}
+
+/// Rules of the conditional rule or of the layer block
+fn rules_mut<'s, 'a>(entity: &'s mut CssEntity<'a>) -> Option<&'s mut CssEntities<'a>> {
+    match entity {
+        CssEntity::Media(Media { entities, .. })
+        | CssEntity::Container(Container { entities, .. })
+        | CssEntity::Supports(Supports { entities, .. })
+        | CssEntity::Layer(Layer {
+            entities: Some(entities),
+            ..
+        }) => Some(entities),
+        _ => None,
+    }
+}
+
+fn append<'a>(target: &mut CssEntity<'a>, mut entity: CssEntity<'a>) {
+    if let (Some(target), Some(entities)) = (rules_mut(target), rules_mut(&mut entity)) {
+        target.0.append(&mut entities.0);
+    }
+}
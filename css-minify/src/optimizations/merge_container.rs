@@ -0,0 +1,92 @@
+use crate::optimizations::merge_conditions::{merge_conditions, Condition};
+use crate::optimizations::replace;
+use crate::optimizations::transformer::Transform;
+use crate::structure::{CssEntities, CssEntity};
+
+#[derive(Default, Debug, Clone)]
+pub struct MergeContainer;
+
+impl Transform for MergeContainer {
+    fn transform<'a>(&self, entity: CssEntity<'a>) -> CssEntity<'a> {
+        match entity {
+            CssEntity::Container(mut c) => {
+                c.condition = replace(c.condition, ": ", ":");
+                CssEntity::Container(c)
+            }
+            e => e,
+        }
+    }
+
+    fn transform_many<'a>(&self, entities: CssEntities<'a>) -> CssEntities<'a> {
+        merge_conditions(self, Condition::Container, entities)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::optimizations::merge_container::MergeContainer;
+    use crate::optimizations::transformer::Transform;
+    use crate::parse;
+    use crate::structure::{Block, Container, CssEntities, CssEntity, Selector, Value};
+
+    fn block(class: &str) -> CssEntity<'_> {
+        CssEntity::Block(Block {
+            selectors: vec![Selector::Class(class.into()).into()].into(),
+            parameters: Default::default(),
+            entities: Default::default(),
+            location: Default::default(),
+        })
+    }
+
+    #[test]
+    fn test_container() {
+        assert_eq!(
+            MergeContainer.transform_many(CssEntities(vec![
+                CssEntity::Container(Container {
+                    name: Some(Value::from("card")),
+                    condition: Value::from("(min-width: 400px)"),
+                    entities: vec![block("test")].into()
+                }),
+                CssEntity::Container(Container {
+                    name: None,
+                    condition: Value::from("(min-width: 400px)"),
+                    entities: vec![block("test2")].into()
+                }),
+                CssEntity::Container(Container {
+                    name: Some(Value::from("card")),
+                    condition: Value::from("(min-width:400px)"),
+                    entities: vec![block("test3")].into()
+                })
+            ])),
+            CssEntities(vec![
+                CssEntity::Container(Container {
+                    name: Some(Value::from("card")),
+                    condition: Value::from("(min-width:400px)"),
+                    entities: vec![block("test"), block("test3")].into()
+                }),
+                CssEntity::Container(Container {
+                    name: None,
+                    condition: Value::from("(min-width:400px)"),
+                    entities: vec![block("test2")].into()
+                })
+            ])
+        )
+    }
+
+    #[test]
+    fn test_order() {
+        let merge = |input| {
+            MergeContainer
+                .transform_many(parse(input).unwrap())
+                .to_string()
+        };
+        assert_eq!(
+            merge("@container (min-width: 1px){a{color:red}}a{color:blue}"),
+            "@container (min-width:1px){a{color:red}}a{color:blue}"
+        );
+        assert_eq!(
+            merge("@container (min-width: 1px){a{color:red}}a{color:blue}@container (min-width:1px){p{color:red}}"),
+            "@container (min-width:1px){a{color:red}p{color:red}}a{color:blue}"
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use crate::optimizations::transformer::Transform;
-use crate::optimizations::{if_some_has_important, none_or_has_important};
+use crate::optimizations::{has_fallbacks, if_some_has_important, none_or_has_important};
 use crate::structure::{Name, Parameters, Value};
 use nom::lib::std::fmt::Formatter;
 use std::fmt::Display;
@@ -7,46 +7,56 @@ use std::fmt::Display;
 #[derive(Clone, Debug, Default)]
 pub struct Merge;
 
+const MARGIN: [&str; 4] = ["margin-top", "margin-bottom", "margin-left", "margin-right"];
+const PADDING: [&str; 4] = [
+    "padding-top",
+    "padding-bottom",
+    "padding-left",
+    "padding-right",
+];
+
 #[derive(Debug, Default, Clone)]
-pub struct Margin(Option<Value>, Option<Value>, Option<Value>, Option<Value>);
+pub struct Margin<'a>(
+    Option<Value<'a>>,
+    Option<Value<'a>>,
+    Option<Value<'a>>,
+    Option<Value<'a>>,
+);
 #[derive(Debug, Default, Clone)]
-pub struct Padding(Option<Value>, Option<Value>, Option<Value>, Option<Value>);
+pub struct Padding<'a>(
+    Option<Value<'a>>,
+    Option<Value<'a>>,
+    Option<Value<'a>>,
+    Option<Value<'a>>,
+);
 
 impl Transform for Merge {
-    fn transform_parameters(&self, mut parameters: Parameters) -> Parameters {
+    fn transform_parameters<'a>(&self, mut parameters: Parameters<'a>) -> Parameters<'a> {
         let mut margin = Margin::default();
         let mut padding = Padding::default();
-        parameters.0.iter().for_each(|(name, val)| {
-            if !parameters.0.contains_key("margin") {
-                margin.add(name, val.clone());
+        parameters.0.iter().for_each(|p| {
+            if !parameters.contains_key("margin") {
+                margin.add(&p.name, p.value.clone());
             }
-            if !parameters.0.contains_key("padding") {
-                padding.add(name, val.clone());
+            if !parameters.contains_key("padding") {
+                padding.add(&p.name, p.value.clone());
             }
         });
 
-        if margin.is_may_be_merged() {
-            parameters.insert(String::from("margin"), margin.to_string());
-            parameters.0.swap_remove("margin-top");
-            parameters.0.swap_remove("margin-bottom");
-            parameters.0.swap_remove("margin-left");
-            parameters.0.swap_remove("margin-right");
+        if margin.is_may_be_merged() && !has_fallbacks(&parameters, &MARGIN) {
+            parameters.replace(&MARGIN, "margin".into(), margin.to_string().into());
         }
-        if padding.is_may_be_merged() {
-            parameters.insert(String::from("padding"), padding.to_string());
-            parameters.0.swap_remove("padding-top");
-            parameters.0.swap_remove("padding-bottom");
-            parameters.0.swap_remove("padding-left");
-            parameters.0.swap_remove("padding-right");
+        if padding.is_may_be_merged() && !has_fallbacks(&parameters, &PADDING) {
+            parameters.replace(&PADDING, "padding".into(), padding.to_string().into());
         }
 
         parameters
     }
 }
 
-impl Margin {
-    fn add(&mut self, name: &Name, value: Value) -> bool {
-        match name.as_str() {
+impl<'a> Margin<'a> {
+    fn add(&mut self, name: &Name, value: Value<'a>) -> bool {
+        match name.as_ref() {
             "margin-top" => {
                 self.0 = Some(value);
                 true
@@ -90,9 +100,9 @@ impl Margin {
     }
 }
 
-impl Padding {
-    fn add(&mut self, name: &Name, value: Value) -> bool {
-        match name.as_str() {
+impl<'a> Padding<'a> {
+    fn add(&mut self, name: &Name, value: Value<'a>) -> bool {
+        match name.as_ref() {
             "padding-top" => {
                 self.0 = Some(value);
                 true
@@ -136,7 +146,7 @@ impl Padding {
     }
 }
 
-impl Display for Margin {
+impl Display for Margin<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let (top, right, bottom, left) = (
             self.0
@@ -175,7 +185,7 @@ impl Display for Margin {
     }
 }
 
-impl Display for Padding {
+impl Display for Padding<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let (top, right, bottom, left) = (
             self.0
@@ -232,8 +242,10 @@ mod test {
                         map.insert("margin-bottom".into(), "3px".into());
                         map.insert("margin-left".into(), "3px".into());
                         map.insert("margin-right".into(), "3px".into());
-                        Parameters(map)
+                        Parameters::from(map)
                     },
+                    entities: Default::default(),
+                    location: Default::default(),
                 }
                 .into()
             ),
@@ -242,8 +254,10 @@ mod test {
                 parameters: {
                     let mut map = IndexMap::new();
                     map.insert("margin".into(), "3px".into());
-                    Parameters(map)
+                    Parameters::from(map)
                 },
+                entities: Default::default(),
+                location: Default::default(),
             }
             .into()
         )
@@ -261,8 +275,10 @@ mod test {
                         map.insert("margin-bottom".into(), "3px".into());
                         map.insert("margin-left".into(), "4px".into());
                         map.insert("margin-right".into(), "4px".into());
-                        Parameters(map)
+                        Parameters::from(map)
                     },
+                    entities: Default::default(),
+                    location: Default::default(),
                 }
                 .into()
             ),
@@ -271,8 +287,10 @@ mod test {
                 parameters: {
                     let mut map = IndexMap::new();
                     map.insert("margin".into(), "3px 4px".into());
-                    Parameters(map)
+                    Parameters::from(map)
                 },
+                entities: Default::default(),
+                location: Default::default(),
             }
             .into()
         )
@@ -290,8 +308,10 @@ mod test {
                         map.insert("margin-bottom".into(), "1px".into());
                         map.insert("margin-left".into(), "4px".into());
                         map.insert("margin-right".into(), "4px".into());
-                        Parameters(map)
+                        Parameters::from(map)
                     },
+                    entities: Default::default(),
+                    location: Default::default(),
                 }
                 .into()
             ),
@@ -300,8 +320,10 @@ mod test {
                 parameters: {
                     let mut map = IndexMap::new();
                     map.insert("margin".into(), "3px 4px 1px".into());
-                    Parameters(map)
+                    Parameters::from(map)
                 },
+                entities: Default::default(),
+                location: Default::default(),
             }
             .into()
         )
@@ -319,8 +341,10 @@ mod test {
                         map.insert("margin-bottom".into(), "1px".into());
                         map.insert("margin-left".into(), "2px".into());
                         map.insert("margin-right".into(), "4px".into());
-                        Parameters(map)
+                        Parameters::from(map)
                     },
+                    entities: Default::default(),
+                    location: Default::default(),
                 }
                 .into()
             ),
@@ -329,8 +353,10 @@ mod test {
                 parameters: {
                     let mut map = IndexMap::new();
                     map.insert("margin".into(), "3px 4px 1px 2px".into());
-                    Parameters(map)
+                    Parameters::from(map)
                 },
+                entities: Default::default(),
+                location: Default::default(),
             }
             .into()
         )
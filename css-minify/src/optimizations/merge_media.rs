@@ -1,71 +1,24 @@
+use crate::optimizations::merge_conditions::{merge_conditions, Condition};
+use crate::optimizations::replace;
 use crate::optimizations::transformer::Transform;
-use crate::structure::{CssEntities, CssEntity, Media, Parameters, Supports};
-use indexmap::map::IndexMap;
+use crate::structure::{CssEntities, CssEntity};
 
 #[derive(Default, Debug, Clone)]
 pub struct MergeMedia;
 
 impl Transform for MergeMedia {
-    fn transform_parameters(&self, parameters: Parameters) -> Parameters {
-        parameters
-    }
-
-    fn transform(&self, entity: CssEntity) -> CssEntity {
+    fn transform<'a>(&self, entity: CssEntity<'a>) -> CssEntity<'a> {
         match entity {
             CssEntity::Media(mut m) => {
-                m.screen = m.screen.replace(": ", ":");
+                m.screen = replace(m.screen, ": ", ":");
                 CssEntity::Media(m)
             }
             e => e,
         }
     }
 
-    fn transform_many(&self, entities: CssEntities) -> CssEntities {
-        let mut media = IndexMap::new();
-        entities
-            .0
-            .iter()
-            .filter_map(|e| match e {
-                CssEntity::Media(m) => Some(m),
-                _ => None,
-            })
-            .for_each(|m| {
-                media
-                    .entry(m.screen.clone())
-                    .and_modify(|media: &mut Media| {
-                        media
-                            .entities
-                            .0
-                            .append(&mut self.transform_many(m.entities.clone()).0);
-                    })
-                    .or_insert(m.clone());
-            });
-        let mut non_media_entities = CssEntities(
-            entities
-                .0
-                .into_iter()
-                .filter(|e| !matches!(e, CssEntity::Media(_)))
-                .map(|e| match e {
-                    CssEntity::Supports(Supports {
-                        conditions,
-                        entities,
-                    }) => Supports {
-                        conditions,
-                        entities: self.transform_many(entities),
-                    }
-                    .into(),
-                    entity => entity,
-                })
-                .collect(),
-        );
-        non_media_entities.0.append(
-            &mut media
-                .into_iter()
-                .map(|(_, m)| m)
-                .map(|m| self.transform(m.into()))
-                .collect(),
-        );
-        non_media_entities
+    fn transform_many<'a>(&self, entities: CssEntities<'a>) -> CssEntities<'a> {
+        merge_conditions(self, Condition::Media, entities)
     }
 }
 
@@ -73,9 +26,8 @@ impl Transform for MergeMedia {
 mod test {
     use crate::optimizations::merge_media::MergeMedia;
     use crate::optimizations::transformer::Transform;
-    use crate::structure::{
-        Block, CssEntities, CssEntity, Media, Selector, SelectorWithPseudoClasses, Value,
-    };
+    use crate::parse;
+    use crate::structure::{Block, CssEntities, CssEntity, Media, Selector, Value};
 
     #[test]
     fn test_media() {
@@ -84,24 +36,20 @@ mod test {
                 CssEntity::Media(Media {
                     screen: Value::from("only screen and (max-width: 992px)"),
                     entities: vec![CssEntity::Block(Block {
-                        selectors: vec![SelectorWithPseudoClasses(
-                            Some(Selector::Class("test".into())),
-                            vec![]
-                        )]
-                        .into(),
-                        parameters: Default::default()
+                        selectors: vec![Selector::Class("test".into()).into()].into(),
+                        parameters: Default::default(),
+                        entities: Default::default(),
+                        location: Default::default(),
                     })]
                     .into()
                 }),
                 CssEntity::Media(Media {
                     screen: Value::from("only screen and (max-width: 992px)"),
                     entities: vec![CssEntity::Block(Block {
-                        selectors: vec![SelectorWithPseudoClasses(
-                            Some(Selector::Class("test2".into())),
-                            vec![]
-                        )]
-                        .into(),
-                        parameters: Default::default()
+                        selectors: vec![Selector::Class("test2".into()).into()].into(),
+                        parameters: Default::default(),
+                        entities: Default::default(),
+                        location: Default::default(),
                     })]
                     .into()
                 })
@@ -110,24 +58,37 @@ mod test {
                 screen: Value::from("only screen and (max-width:992px)"),
                 entities: vec![
                     CssEntity::Block(Block {
-                        selectors: vec![SelectorWithPseudoClasses(
-                            Some(Selector::Class("test".into())),
-                            vec![]
-                        )]
-                        .into(),
-                        parameters: Default::default()
+                        selectors: vec![Selector::Class("test".into()).into()].into(),
+                        parameters: Default::default(),
+                        entities: Default::default(),
+                        location: Default::default(),
                     }),
                     CssEntity::Block(Block {
-                        selectors: vec![SelectorWithPseudoClasses(
-                            Some(Selector::Class("test2".into())),
-                            vec![]
-                        )]
-                        .into(),
-                        parameters: Default::default()
+                        selectors: vec![Selector::Class("test2".into()).into()].into(),
+                        parameters: Default::default(),
+                        entities: Default::default(),
+                        location: Default::default(),
                     })
                 ]
                 .into()
             })])
         )
     }
+
+    #[test]
+    fn test_order() {
+        let merge = |input| MergeMedia.transform_many(parse(input).unwrap()).to_string();
+        assert_eq!(
+            merge("@media print{a{color:red}}a{color:blue}"),
+            "@media print{a{color:red}}a{color:blue}"
+        );
+        assert_eq!(
+            merge("@media print{a{color:red}}a{color:blue}@media print{b{color:red}}"),
+            "@media print{a{color:red}b{color:red}}a{color:blue}"
+        );
+        assert_eq!(
+            merge("@media print{a{color:red}}a{color:blue}@media print{a{margin:0}a{color:green}}"),
+            "@media print{a{color:red}}a{color:blue}@media print{a{margin:0}a{color:green}}"
+        );
+    }
 }
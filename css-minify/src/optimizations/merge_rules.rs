@@ -0,0 +1,307 @@
+use crate::optimizations::cascade::{families_conflict, property_family, Declared};
+use crate::optimizations::transformer::Transform;
+use crate::structure::{
+    Attribute, Block, ComplexSelector, Container, CssEntities, CssEntity, Layer, Media, Parameter,
+    Parameters, PseudoClass, PseudoElement, Selector, Selectors, Supports,
+};
+use std::collections::HashMap;
+
+/// Pseudo classes and elements of css2, which are supported by every browser
+const CSS2_PSEUDOS: [&str; 11] = [
+    "link",
+    "visited",
+    "hover",
+    "active",
+    "focus",
+    "first-child",
+    "lang",
+    "before",
+    "after",
+    "first-line",
+    "first-letter",
+];
+
+/// Merges css blocks with equal declarations into one block with both selector lists:
+/// `.a{color:red}.b{color:red}` to `.a,.b{color:red}`. Blocks are merged when no rule
+/// between them may set the same properties for the same elements. Neighbouring blocks
+/// which share only some declarations are split when the output becomes smaller
+#[derive(Default, Debug, Clone)]
+pub struct MergeRules;
+
+impl Transform for MergeRules {
+    fn transform<'a>(&self, entity: CssEntity<'a>) -> CssEntity<'a> {
+        entity
+    }
+
+    fn transform_many<'a>(&self, entities: CssEntities<'a>) -> CssEntities<'a> {
+        let mut merged: Vec<CssEntity<'a>> = Vec::with_capacity(entities.len());
+        // last block with the declarations, the blocks which are split are removed from it
+        let mut targets = HashMap::new();
+        for entity in entities.0 {
+            let block = match entity {
+                CssEntity::Block(mut block) => {
+                    block.entities = self.transform_many(block.entities);
+                    block
+                }
+                entity => {
+                    merged.push(self.transform_nested(entity));
+                    continue;
+                }
+            };
+            if !is_mergeable(&block) {
+                merged.push(block.into());
+                continue;
+            }
+            let key = block.parameters.to_string();
+            if let Some(&target) = targets.get(&key) {
+                if can_move(&merged[target + 1..], &block) {
+                    if let CssEntity::Block(target) = &mut merged[target] {
+                        union(&mut target.selectors, block.selectors);
+                    }
+                    continue;
+                }
+            }
+            if let Some(CssEntity::Block(previous)) = merged.last() {
+                if is_mergeable(previous) {
+                    if let Some(split) = split_shared(previous, &block) {
+                        merged.pop();
+                        targets.retain(|_, &mut target| target != merged.len());
+                        split.into_iter().for_each(|block| {
+                            targets.insert(block.parameters.to_string(), merged.len());
+                            merged.push(block.into());
+                        });
+                        continue;
+                    }
+                }
+            }
+            targets.insert(key, merged.len());
+            merged.push(block.into());
+        }
+        merged.into()
+    }
+}
+
+impl MergeRules {
+    /// Blocks are merged inside of conditional rules and layers on their own
+    fn transform_nested<'a>(&self, entity: CssEntity<'a>) -> CssEntity<'a> {
+        match entity {
+            CssEntity::Media(Media { screen, entities }) => Media {
+                screen,
+                entities: self.transform_many(entities),
+            }
+            .into(),
+            CssEntity::Container(Container {
+                name,
+                condition,
+                entities,
+            }) => Container {
+                name,
+                condition,
+                entities: self.transform_many(entities),
+            }
+            .into(),
+            CssEntity::Supports(Supports {
+                conditions,
+                entities,
+            }) => Supports {
+                conditions,
+                entities: self.transform_many(entities),
+            }
+            .into(),
+            CssEntity::Layer(Layer { names, entities }) => Layer {
+                names,
+                entities: entities.map(|entities| self.transform_many(entities)),
+            }
+            .into(),
+            entity => entity,
+        }
+    }
+}
+
+/// Blocks with nested rules and bare blocks are kept as is. A selector list is dropped by browsers
+/// which don't support one of its selectors, so only the selectors supported everywhere are merged
+fn is_mergeable(block: &Block) -> bool {
+    block.entities.is_empty()
+        && !block.is_bare()
+        && !block.parameters.is_empty()
+        && block.selectors.iter().all(is_supported_everywhere)
+}
+
+/// Type, class, id and attribute selectors with combinators and css2 pseudo classes and elements
+fn is_supported_everywhere(selector: &ComplexSelector) -> bool {
+    let is_css2 = |name: &str| CSS2_PSEUDOS.contains(&name.to_ascii_lowercase().as_str());
+    selector.iter().all(|(_, compound)| {
+        compound.iter().all(|selector| match selector {
+            Selector::Tag(tag) => !tag.contains('|'),
+            Selector::Id(_) | Selector::Class(_) => true,
+            Selector::Attribute(Attribute { matcher, .. }) => {
+                !matches!(matcher, Some(matcher) if matcher.flag.is_some())
+            }
+            Selector::PseudoClass(PseudoClass { name, argument }) => {
+                is_css2(name) && (argument.is_none() || name.eq_ignore_ascii_case("lang"))
+            }
+            Selector::PseudoElement(PseudoElement {
+                name,
+                argument: None,
+                ..
+            }) => is_css2(name),
+            _ => false,
+        })
+    })
+}
+
+/// Declarations of the block may be moved before the rules
+fn can_move(between: &[CssEntity], block: &Block) -> bool {
+    let mut declared = Declared::default();
+    between.iter().for_each(|entity| declared.add(entity));
+    block
+        .parameters
+        .iter()
+        .all(|p| !declared.conflicts(&block.selectors, &property_family(&p.name)))
+}
+
+fn union<'a>(selectors: &mut Selectors<'a>, other: Selectors<'a>) {
+    for selector in other.0 {
+        if !selectors.contains(&selector) {
+            selectors.push(selector);
+        }
+    }
+}
+
+/// Moves declarations shared by the neighbouring blocks into a block with both selector
+/// lists between them: `.a{color:red;margin:0}.b{color:red}` to `.a{margin:0}.a,.b{color:red}`.
+/// The other declarations must not set the same properties, then their order doesn't matter
+fn split_shared<'a>(first: &Block<'a>, second: &Block<'a>) -> Option<Vec<Block<'a>>> {
+    let (shared, first_only): (Vec<_>, Vec<_>) = first
+        .parameters
+        .iter()
+        .cloned()
+        .partition(|p| second.parameters.contains(p));
+    let second_only = second
+        .parameters
+        .iter()
+        .filter(|p| !shared.contains(p))
+        .cloned()
+        .collect::<Vec<_>>();
+    let shared_families = families(&shared);
+    let conflicts = families(&first_only)
+        .iter()
+        .chain(&families(&second_only))
+        .any(|family| shared_families.iter().any(|s| families_conflict(s, family)));
+    if shared.is_empty() || conflicts {
+        return None;
+    }
+
+    let mut selectors = first.selectors.clone();
+    union(&mut selectors, second.selectors.clone());
+    let split = [
+        with_parameters(first, first_only),
+        Some(Block {
+            selectors,
+            parameters: shared.into(),
+            entities: Default::default(),
+            location: first.location,
+        }),
+        with_parameters(second, second_only),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+    let length = |blocks: &[&Block]| blocks.iter().map(|b| b.to_string().len()).sum::<usize>();
+    match length(&split.iter().collect::<Vec<_>>()) < length(&[first, second]) {
+        true => Some(split),
+        false => None,
+    }
+}
+
+fn families(parameters: &[Parameter]) -> Vec<String> {
+    parameters
+        .iter()
+        .map(|p| property_family(&p.name))
+        .collect()
+}
+
+fn with_parameters<'a>(block: &Block<'a>, parameters: Vec<Parameter<'a>>) -> Option<Block<'a>> {
+    (!parameters.is_empty()).then(|| Block {
+        selectors: block.selectors.clone(),
+        parameters: Parameters(parameters),
+        entities: Default::default(),
+        location: block.location,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::optimizations::merge_rules::MergeRules;
+    use crate::optimizations::transformer::Transform;
+    use crate::parse;
+
+    fn merge(input: &str) -> String {
+        MergeRules.transform_many(parse(input).unwrap()).to_string()
+    }
+
+    #[test]
+    fn test_merge_rules() {
+        assert_eq!(merge(".a{color:red}.b{color:red}"), ".a,.b{color:red}");
+        assert_eq!(
+            merge(".a{color:red}.c{margin:0}.b{color:red}.a{color:red}"),
+            ".a,.b{color:red}.c{margin:0}"
+        );
+        assert_eq!(
+            merge(".a{color:red}.c{color:blue}.b{color:red}"),
+            ".a{color:red}.c{color:blue}.b{color:red}"
+        );
+        assert_eq!(
+            merge(".a{color:red}@media print{.b{color:blue}}.b{color:red}"),
+            ".a{color:red}@media print{.b{color:blue}}.b{color:red}"
+        );
+        assert_eq!(
+            merge(".a{color:red}@media print{p{margin:0}}.b{color:red}::-moz-selection{color:red}"),
+            ".a,.b{color:red}@media print{p{margin:0}}::-moz-selection{color:red}"
+        );
+        assert_eq!(
+            merge("a:hover,p:first-child{color:red}[href]::before{color:red}a:lang(en) > b{color:red}"),
+            "a:hover,p:first-child,[href]::before,a:lang(en) > b{color:red}"
+        );
+        assert_eq!(
+            merge("a{color:red}a:has(>img){color:red}a:focus-visible{color:red}[x=y i]{color:red}"),
+            "a{color:red}a:has(>img){color:red}a:focus-visible{color:red}[x=y i]{color:red}"
+        );
+        assert_eq!(
+            merge(".a{.b{color:red}color:red}"),
+            ".a{.b{color:red}color:red}"
+        );
+    }
+
+    #[test]
+    fn test_merge_shared() {
+        assert_eq!(
+            merge(".first{color:red;background:#000}.second{color:red;background:#000;margin:0}"),
+            ".first,.second{color:red;background:#000}.second{margin:0}"
+        );
+        assert_eq!(
+            merge(".a{padding:0;color:red}.b{color:red}"),
+            ".a{padding:0}.a,.b{color:red}"
+        );
+        assert_eq!(
+            merge(".first-long-name{padding:0;color:red}.second-long-name{color:red}"),
+            ".first-long-name{padding:0;color:red}.second-long-name{color:red}"
+        );
+        assert_eq!(
+            merge(".first{margin:0;color:red;background:#000}.second{margin-top:1px;color:red;background:#000}"),
+            ".first{margin:0}.first,.second{color:red;background:#000}.second{margin-top:1px}"
+        );
+        assert_eq!(
+            merge(".first{margin:0;color:red;background:#000}.second{margin:1px;color:red;background:#000}"),
+            ".first{margin:0}.first,.second{color:red;background:#000}.second{margin:1px}"
+        );
+        assert_eq!(
+            merge(".first{margin:0;color:red;background:#000}.second{color:blue;color:red;background:#000}"),
+            ".first{margin:0;color:red;background:#000}.second{color:blue;color:red;background:#000}"
+        );
+        assert_eq!(
+            merge("a.x{color:red;margin:0}a.y{color:red}p{color:red;margin:0}"),
+            "a.x{margin:0}a.x,a.y,p{color:red}p{margin:0}"
+        );
+    }
+}
@@ -1,14 +1,39 @@
 use crate::optimizations::transformer::Transform;
-use crate::optimizations::{if_some_has_important, none_or_has_important};
-use crate::structure::{Name, Parameters, Value};
+use crate::optimizations::{has_fallbacks, if_some_has_important, none_or_has_important};
+use crate::structure::{Name, Parameter, Parameters, Value};
 use nom::lib::std::fmt::Formatter;
 use std::fmt::Display;
 
 #[derive(Default, Debug, Clone)]
 pub struct MergeShortHand;
 
+const FONT: [&str; 6] = [
+    "font-style",
+    "font-variant",
+    "font-weight",
+    "font-size",
+    "line-height",
+    "font-family",
+];
+const LIST_STYLE: [&str; 3] = ["list-style-type", "list-style-position", "list-style-image"];
+const BACKGROUND: [&str; 5] = [
+    "background-attachment",
+    "background-color",
+    "background-position",
+    "background-repeat",
+    "background-image",
+];
+const BORDER: [&str; 3] = ["border-width", "border-style", "border-color"];
+const OUTLINE: [&str; 3] = ["outline-width", "outline-style", "outline-color"];
+const TRANSITION: [&str; 4] = [
+    "transition-property",
+    "transition-duration",
+    "transition-delay",
+    "transition-timing-function",
+];
+
 impl Transform for MergeShortHand {
-    fn transform_parameters(&self, mut parameters: Parameters) -> Parameters {
+    fn transform_parameters<'a>(&self, mut parameters: Parameters<'a>) -> Parameters<'a> {
         let mut font = FontShortHand::default();
         let mut list = ListShortHand::default();
         let mut background = BackgroundShortHand::default();
@@ -19,90 +44,73 @@ impl Transform for MergeShortHand {
         parameters
             .0
             .iter()
-            .for_each(|(name, value): (&Name, &Value)| {
-                if !parameters.0.contains_key("font") {
+            .for_each(|Parameter { name, value, .. }| {
+                if !parameters.contains_key("font") {
                     font.add(name, value.clone());
                 }
-                if !parameters.0.contains_key("list-style") {
+                if !parameters.contains_key("list-style") {
                     list.add(name, value.clone());
                 }
-                if !parameters.0.contains_key("background") {
+                if !parameters.contains_key("background") {
                     background.add(name, value.clone());
                 }
-                if !parameters.0.contains_key("border") {
+                if !parameters.contains_key("border") {
                     border.add(name, value.clone());
                 }
-                if !parameters.0.contains_key("outline") {
+                if !parameters.contains_key("outline") {
                     outline.add(name, value.clone());
                 }
-                if !parameters.0.contains_key("transition") {
+                if !parameters.contains_key("transition") {
                     transition.add(name, value.clone());
                 }
             });
 
-        if font.is_maybe_shorted() {
-            parameters
-                .0
-                .insert(String::from("font"), font.to_string().trim().to_string());
-            parameters.0.swap_remove("font-style");
-            parameters.0.swap_remove("font-variant");
-            parameters.0.swap_remove("font-weight");
-            parameters.0.swap_remove("font-size");
-            parameters.0.swap_remove("line-height");
-            parameters.0.swap_remove("font-family");
-        }
-
-        if list.is_maybe_shorted() {
-            parameters.0.insert(
-                String::from("list-style"),
-                list.to_string().trim().to_string(),
+        if font.is_maybe_shorted() && !has_fallbacks(&parameters, &FONT) {
+            parameters.replace(
+                &FONT,
+                "font".into(),
+                font.to_string().trim().to_string().into(),
+            );
+        }
+
+        if list.is_maybe_shorted() && !has_fallbacks(&parameters, &LIST_STYLE) {
+            parameters.replace(
+                &LIST_STYLE,
+                "list-style".into(),
+                list.to_string().trim().to_string().into(),
             );
-            parameters.0.swap_remove("list-style-type");
-            parameters.0.swap_remove("list-style-position");
-            parameters.0.swap_remove("list-style-image");
         }
 
-        if background.is_maybe_shorted() {
-            parameters.0.insert(
-                String::from("background"),
-                background.to_string().trim().to_string(),
+        if background.is_maybe_shorted() && !has_fallbacks(&parameters, &BACKGROUND) {
+            parameters.replace(
+                &BACKGROUND,
+                "background".into(),
+                background.to_string().trim().to_string().into(),
             );
-            parameters.0.swap_remove("background-attachment");
-            parameters.0.swap_remove("background-color");
-            parameters.0.swap_remove("background-position");
-            parameters.0.swap_remove("background-repeat");
-            parameters.0.swap_remove("background-image");
         }
 
-        if border.is_maybe_shorted() {
-            parameters.0.insert(
-                String::from("border"),
-                border.to_string().trim().to_string(),
+        if border.is_maybe_shorted() && !has_fallbacks(&parameters, &BORDER) {
+            parameters.replace(
+                &BORDER,
+                "border".into(),
+                border.to_string().trim().to_string().into(),
             );
-            parameters.0.swap_remove("border-width");
-            parameters.0.swap_remove("border-style");
-            parameters.0.swap_remove("border-color");
         }
 
-        if outline.is_maybe_shorted() {
-            parameters.0.insert(
-                String::from("outline"),
-                outline.to_string().trim().to_string(),
+        if outline.is_maybe_shorted() && !has_fallbacks(&parameters, &OUTLINE) {
+            parameters.replace(
+                &OUTLINE,
+                "outline".into(),
+                outline.to_string().trim().to_string().into(),
             );
-            parameters.0.swap_remove("outline-width");
-            parameters.0.swap_remove("outline-style");
-            parameters.0.swap_remove("outline-color");
         }
 
-        if transition.is_maybe_shorted() {
-            parameters.0.insert(
-                String::from("transition"),
-                transition.to_string().trim().to_string(),
+        if transition.is_maybe_shorted() && !has_fallbacks(&parameters, &TRANSITION) {
+            parameters.replace(
+                &TRANSITION,
+                "transition".into(),
+                transition.to_string().trim().to_string().into(),
             );
-            parameters.0.swap_remove("transition-property");
-            parameters.0.swap_remove("transition-duration");
-            parameters.0.swap_remove("transition-delay");
-            parameters.0.swap_remove("transition-timing-function");
         }
 
         parameters
@@ -110,16 +118,16 @@ impl Transform for MergeShortHand {
 }
 
 #[derive(Debug, Default)]
-struct FontShortHand {
-    font_style: Option<Value>,
-    font_variant: Option<Value>,
-    font_weight: Option<Value>,
-    font_size: Option<Value>,
-    line_height: Option<Value>,
-    font_family: Option<Value>,
+struct FontShortHand<'a> {
+    font_style: Option<Value<'a>>,
+    font_variant: Option<Value<'a>>,
+    font_weight: Option<Value<'a>>,
+    font_size: Option<Value<'a>>,
+    line_height: Option<Value<'a>>,
+    font_family: Option<Value<'a>>,
 }
 
-impl FontShortHand {
+impl<'a> FontShortHand<'a> {
     fn is_maybe_shorted(&self) -> bool {
         self.font_size.is_some()
             && self.font_family.is_some()
@@ -144,8 +152,8 @@ impl FontShortHand {
             || none_or_has_important(self.font_family.as_ref()))
     }
 
-    fn add(&mut self, name: &Name, value: Value) {
-        match name.as_str() {
+    fn add(&mut self, name: &Name, value: Value<'a>) {
+        match name.as_ref() {
             "font-style" => self.font_style = Some(value),
             "font-variant" => self.font_variant = Some(value),
             "font-weight" => self.font_weight = Some(value),
@@ -157,7 +165,7 @@ impl FontShortHand {
     }
 }
 
-impl Display for FontShortHand {
+impl Display for FontShortHand<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         if let Some(v) = &self.font_style {
             write!(f, "{}", v.trim_end_matches("!important").trim())?;
@@ -185,13 +193,13 @@ impl Display for FontShortHand {
 }
 
 #[derive(Debug, Default)]
-struct ListShortHand {
-    list_style_type: Option<Value>,
-    list_style_position: Option<Value>,
-    list_style_image: Option<Value>,
+struct ListShortHand<'a> {
+    list_style_type: Option<Value<'a>>,
+    list_style_position: Option<Value<'a>>,
+    list_style_image: Option<Value<'a>>,
 }
 
-impl ListShortHand {
+impl<'a> ListShortHand<'a> {
     fn is_maybe_shorted(&self) -> bool {
         (self.list_style_type.is_some()
             || self.list_style_position.is_some()
@@ -211,8 +219,8 @@ impl ListShortHand {
             || none_or_has_important(self.list_style_image.as_ref()))
     }
 
-    fn add(&mut self, name: &Name, value: Value) {
-        match name.as_str() {
+    fn add(&mut self, name: &Name, value: Value<'a>) {
+        match name.as_ref() {
             "list-style-type" => self.list_style_type = Some(value),
             "list-style-position" => self.list_style_position = Some(value),
             "list-style-image" => self.list_style_image = Some(value),
@@ -221,7 +229,7 @@ impl ListShortHand {
     }
 }
 
-impl Display for ListShortHand {
+impl Display for ListShortHand<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         if let Some(v) = &self.list_style_type {
             write!(f, "{}", v.trim_end_matches("!important").trim())?;
@@ -240,15 +248,15 @@ impl Display for ListShortHand {
 }
 
 #[derive(Debug, Default)]
-struct BackgroundShortHand {
-    background_color: Option<Value>,
-    background_image: Option<Value>,
-    background_repeat: Option<Value>,
-    background_attachment: Option<Value>,
-    background_position: Option<Value>,
+struct BackgroundShortHand<'a> {
+    background_color: Option<Value<'a>>,
+    background_image: Option<Value<'a>>,
+    background_repeat: Option<Value<'a>>,
+    background_attachment: Option<Value<'a>>,
+    background_position: Option<Value<'a>>,
 }
 
-impl BackgroundShortHand {
+impl<'a> BackgroundShortHand<'a> {
     fn is_maybe_shorted(&self) -> bool {
         (self.background_color.is_some()
             || self.background_image.is_some()
@@ -274,8 +282,8 @@ impl BackgroundShortHand {
             || none_or_has_important(self.background_position.as_ref()))
     }
 
-    fn add(&mut self, name: &Name, value: Value) {
-        match name.as_str() {
+    fn add(&mut self, name: &Name, value: Value<'a>) {
+        match name.as_ref() {
             "background-color" => self.background_color = Some(value),
             "background-image" => self.background_image = Some(value),
             "background-repeat" => self.background_repeat = Some(value),
@@ -286,7 +294,7 @@ impl BackgroundShortHand {
     }
 }
 
-impl Display for BackgroundShortHand {
+impl Display for BackgroundShortHand<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         if let Some(v) = &self.background_color {
             write!(f, " {}", v.trim_end_matches("!important").trim())?;
@@ -312,13 +320,13 @@ impl Display for BackgroundShortHand {
 }
 
 #[derive(Debug, Default)]
-struct BorderShortHand {
-    border_width: Option<Value>,
-    border_style: Option<Value>,
-    border_color: Option<Value>,
+struct BorderShortHand<'a> {
+    border_width: Option<Value<'a>>,
+    border_style: Option<Value<'a>>,
+    border_color: Option<Value<'a>>,
 }
 
-impl BorderShortHand {
+impl<'a> BorderShortHand<'a> {
     fn is_maybe_shorted(&self) -> bool {
         (self.border_width.is_some() || self.border_style.is_some() || self.border_color.is_some())
             && (self.all_elements_has_important() || self.no_one_element_has_no_important())
@@ -336,8 +344,8 @@ impl BorderShortHand {
             || none_or_has_important(self.border_color.as_ref()))
     }
 
-    fn add(&mut self, name: &Name, value: Value) {
-        match name.as_str() {
+    fn add(&mut self, name: &Name, value: Value<'a>) {
+        match name.as_ref() {
             "border-width" => self.border_width = Some(value),
             "border-style" => self.border_style = Some(value),
             "border-color" => self.border_color = Some(value),
@@ -346,7 +354,7 @@ impl BorderShortHand {
     }
 }
 
-impl Display for BorderShortHand {
+impl Display for BorderShortHand<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         if let Some(v) = &self.border_width {
             write!(f, "{}", v.trim_end_matches("!important").trim())?;
@@ -366,13 +374,13 @@ impl Display for BorderShortHand {
 }
 
 #[derive(Debug, Default)]
-struct OutlineShortHand {
-    outline_width: Option<Value>,
-    outline_style: Option<Value>,
-    outline_color: Option<Value>,
+struct OutlineShortHand<'a> {
+    outline_width: Option<Value<'a>>,
+    outline_style: Option<Value<'a>>,
+    outline_color: Option<Value<'a>>,
 }
 
-impl OutlineShortHand {
+impl<'a> OutlineShortHand<'a> {
     fn is_maybe_shorted(&self) -> bool {
         (self.outline_width.is_some()
             || self.outline_style.is_some()
@@ -392,8 +400,8 @@ impl OutlineShortHand {
             || none_or_has_important(self.outline_color.as_ref()))
     }
 
-    fn add(&mut self, name: &Name, value: Value) {
-        match name.as_str() {
+    fn add(&mut self, name: &Name, value: Value<'a>) {
+        match name.as_ref() {
             "outline-width" => self.outline_width = Some(value),
             "outline-style" => self.outline_style = Some(value),
             "outline-color" => self.outline_color = Some(value),
@@ -402,7 +410,7 @@ impl OutlineShortHand {
     }
 }
 
-impl Display for OutlineShortHand {
+impl Display for OutlineShortHand<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         if let Some(v) = &self.outline_width {
             write!(f, "{}", v.trim_end_matches("!important").trim())?;
@@ -422,14 +430,14 @@ impl Display for OutlineShortHand {
 }
 
 #[derive(Debug, Default)]
-struct TransitionShortHand {
-    transition_property: Option<Value>,
-    transition_duration: Option<Value>,
-    transition_delay: Option<Value>,
-    transition_timing_function: Option<Value>,
+struct TransitionShortHand<'a> {
+    transition_property: Option<Value<'a>>,
+    transition_duration: Option<Value<'a>>,
+    transition_delay: Option<Value<'a>>,
+    transition_timing_function: Option<Value<'a>>,
 }
 
-impl TransitionShortHand {
+impl<'a> TransitionShortHand<'a> {
     fn is_maybe_shorted(&self) -> bool {
         (self.transition_property.is_some()
             || self.transition_duration.is_some()
@@ -452,8 +460,8 @@ impl TransitionShortHand {
             || none_or_has_important(self.transition_timing_function.as_ref()))
     }
 
-    fn add(&mut self, name: &Name, value: Value) {
-        match name.as_str() {
+    fn add(&mut self, name: &Name, value: Value<'a>) {
+        match name.as_ref() {
             "transition-property" => self.transition_property = Some(value),
             "transition-duration" => self.transition_duration = Some(value),
             "transition-delay" => self.transition_delay = Some(value),
@@ -463,7 +471,7 @@ impl TransitionShortHand {
     }
 }
 
-impl Display for TransitionShortHand {
+impl Display for TransitionShortHand<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         if let Some(v) = &self.transition_property {
             write!(f, "{}", v.trim_end_matches("!important").trim())?;
@@ -505,8 +513,10 @@ mod test {
                         map.insert("font-size".into(), ".8em".into());
                         map.insert("line-height".into(), "1.2".into());
                         map.insert("font-family".into(), "Arial, sans-serif".into());
-                        Parameters(map)
+                        Parameters::from(map)
                     },
+                    entities: Default::default(),
+                    location: Default::default(),
                 }
                 .into()
             ),
@@ -518,8 +528,10 @@ mod test {
                         "font".into(),
                         "italic bold .8em/1.2 Arial, sans-serif".into(),
                     );
-                    Parameters(map)
+                    Parameters::from(map)
                 },
+                entities: Default::default(),
+                location: Default::default(),
             }
             .into()
         )
@@ -537,8 +549,10 @@ mod test {
                         map.insert("background-image".into(), "url(images/bg.gif)".into());
                         map.insert("background-repeat".into(), "no-repeat".into());
                         map.insert("background-position".into(), "left top".into());
-                        Parameters(map)
+                        Parameters::from(map)
                     },
+                    entities: Default::default(),
+                    location: Default::default(),
                 }
                 .into()
             ),
@@ -550,8 +564,10 @@ mod test {
                         "background".into(),
                         "#000 url(images/bg.gif) no-repeat left top".into(),
                     );
-                    Parameters(map)
+                    Parameters::from(map)
                 },
+                entities: Default::default(),
+                location: Default::default(),
             }
             .into()
         )
@@ -566,8 +582,10 @@ mod test {
                     parameters: {
                         let mut map = IndexMap::new();
                         map.insert("background-color".into(), "#000 !important".into());
-                        Parameters(map)
+                        Parameters::from(map)
                     },
+                    entities: Default::default(),
+                    location: Default::default(),
                 }
                 .into()
             ),
@@ -576,8 +594,10 @@ mod test {
                 parameters: {
                     let mut map = IndexMap::new();
                     map.insert("background".into(), "#000!important".into());
-                    Parameters(map)
+                    Parameters::from(map)
                 },
+                entities: Default::default(),
+                location: Default::default(),
             }
             .into()
         )
@@ -594,8 +614,10 @@ mod test {
                         map.insert("border-width".into(), "1px".into());
                         map.insert("border-style".into(), "solid".into());
                         map.insert("border-color".into(), "#000".into());
-                        Parameters(map)
+                        Parameters::from(map)
                     },
+                    entities: Default::default(),
+                    location: Default::default(),
                 }
                 .into()
             ),
@@ -604,8 +626,10 @@ mod test {
                 parameters: {
                     let mut map = IndexMap::new();
                     map.insert("border".into(), "1px solid #000".into());
-                    Parameters(map)
+                    Parameters::from(map)
                 },
+                entities: Default::default(),
+                location: Default::default(),
             }
             .into()
         )
@@ -622,8 +646,10 @@ mod test {
                         map.insert("outline-width".into(), "1px".into());
                         map.insert("outline-style".into(), "solid".into());
                         map.insert("outline-color".into(), "#000".into());
-                        Parameters(map)
+                        Parameters::from(map)
                     },
+                    entities: Default::default(),
+                    location: Default::default(),
                 }
                 .into()
             ),
@@ -632,8 +658,10 @@ mod test {
                 parameters: {
                     let mut map = IndexMap::new();
                     map.insert("outline".into(), "1px solid #000".into());
-                    Parameters(map)
+                    Parameters::from(map)
                 },
+                entities: Default::default(),
+                location: Default::default(),
             }
             .into()
         )
@@ -1,109 +1,124 @@
+mod builder;
+mod cascade;
 mod color;
+mod duplicates;
 mod font;
 mod merge_blocks;
+mod merge_conditions;
+mod merge_container;
 mod merge_m_n_p;
 mod merge_media;
+mod merge_rules;
 mod merge_shorthand;
+mod nesting;
+mod numbers;
+mod parallel;
+mod selectors;
+mod stream;
 mod transformer;
-
-use crate::optimizations::color::optimize_color;
-use crate::optimizations::font::FontTransformer;
-use crate::optimizations::merge_blocks::MergeBlocks;
-use crate::optimizations::merge_m_n_p::Merge;
-use crate::optimizations::merge_media::MergeMedia;
-use crate::optimizations::merge_shorthand::MergeShortHand;
-use crate::optimizations::transformer::{Transform, Transformer, TransformerParameterFn};
-use crate::parsers::css_entity::parse_css;
-use crate::structure::Value;
-use derive_more::{From, Into};
+mod values;
+
+pub use crate::optimizations::builder::{MinifierBuilder, Pass};
+pub use crate::optimizations::stream::StreamError;
+pub use crate::optimizations::transformer::{
+    DeclarationContext, Transform, Transformer, TransformerParameterFn,
+};
+
+use crate::optimizations::builder::Stage;
+use crate::optimizations::parallel::{transform_parallel, transform_stages};
+use crate::parsers::css_entity::entity_error;
+use crate::source_map::{write_with_source_map, SourceMap, SourceMapOptions};
+use crate::structure::{Parameters, Stylesheet, Value};
+use crate::{parse, parse_tolerant};
 use nom::lib::std::fmt::Debug;
 use nom::lib::std::str::FromStr;
-use nom::{Err, Needed};
+use nom::Err;
+use std::borrow::Cow;
 use std::error::Error;
 use std::fmt::Display;
 use std::fmt::Formatter;
 
-/// Struct which stores all optimizations from css minify lib
+/// Pipeline of optimizations from css minify lib, see [`MinifierBuilder`] to change it
 pub struct Minifier {
-    transformer: Transformer,
-    merge_m_n_p: Merge,
-    merge_shorthand: MergeShortHand,
-    media: MergeMedia,
-    blocks: MergeBlocks,
-    font: FontTransformer,
+    stages: Vec<Stage>,
+    threads: usize,
 }
 
 impl Minifier {
-    /// Minify css input and return result with minified css string
-    pub fn minify<'a>(&self, input: &'a str, level: Level) -> MResult<'a> {
-        let mut result = parse_css(input)
-            .map(|(_, blocks)| blocks)
-            .map_err(|e| MError(input, e));
-
-        if level == Level::Three {
-            result = result
-                .map(|blocks| self.blocks.transform_many(blocks))
-                .map(|blocks| self.media.transform_many(blocks))
-        }
+    pub fn builder() -> MinifierBuilder {
+        MinifierBuilder::default()
+    }
 
-        if level >= Level::Two {
-            result = result
-                .map(|blocks| self.merge_m_n_p.transform_many(blocks))
-                .map(|blocks| self.merge_shorthand.transform_many(blocks))
-        }
+    /// Rewrite nested rules into plain selectors for browsers without css nesting support
+    pub fn flatten_nesting(self, flatten: bool) -> Self {
+        self.toggle(Pass::FlattenNesting, flatten)
+    }
+
+    /// Sort selectors of every selector list, the source order is kept otherwise
+    pub fn sort_selectors(self, sort: bool) -> Self {
+        self.toggle(Pass::SortSelectors, sort)
+    }
 
-        if level >= Level::One {
-            result = result
-                .map(|blocks| self.transformer.transform_many(blocks))
-                .map(|blocks| self.font.transform_many(blocks))
+    /// Transform top level rules on the given number of threads,
+    /// see [`MinifierBuilder::threads`]
+    pub fn threads(self, threads: usize) -> Self {
+        MinifierBuilder::from(self).threads(threads).build()
+    }
+
+    fn toggle(self, pass: Pass, enable: bool) -> Self {
+        let builder = MinifierBuilder::from(self);
+        match enable {
+            true => builder.enable(pass, Level::Zero),
+            false => builder.disable(pass),
         }
+        .build()
+    }
+
+    /// Minify css input and return result with minified css string
+    pub fn minify(&self, input: &str, level: Level) -> MResult {
+        parse(input).map(|stylesheet| self.optimize(stylesheet, level).to_string())
+    }
+
+    /// Minify css input and return minified css with the source map pointing back to the input
+    pub fn minify_with_source_map(
+        &self,
+        input: &str,
+        level: Level,
+        options: &SourceMapOptions,
+    ) -> Result<(String, SourceMap), MError> {
+        parse(input).map(|stylesheet| {
+            write_with_source_map(&self.optimize(stylesheet, level), input, options)
+        })
+    }
 
-        result.map(|blocks| blocks.to_string())
+    /// Minify css input skipping invalid declarations and rules,
+    /// returns minified css with the list of the skipped parts
+    pub fn minify_tolerant(&self, input: &str, level: Level) -> (String, Vec<MError>) {
+        let (stylesheet, diagnostics) = parse_tolerant(input);
+        (self.optimize(stylesheet, level).to_string(), diagnostics)
+    }
+
+    /// Apply level-dependent optimizations to an already parsed stylesheet
+    pub fn optimize<'a>(&self, stylesheet: Stylesheet<'a>, level: Level) -> Stylesheet<'a> {
+        let stages = self
+            .stages
+            .iter()
+            .filter(|stage| stage.level <= level)
+            .collect::<Vec<_>>();
+        stages
+            .chunk_by(|a, b| a.is_per_declaration() == b.is_per_declaration())
+            .fold(stylesheet, |stylesheet, stages| {
+                match self.threads > 1 && stages[0].is_per_declaration() {
+                    true => transform_parallel(stages, stylesheet, self.threads),
+                    false => transform_stages(stages, stylesheet),
+                }
+            })
     }
 }
 
 impl Default for Minifier {
     fn default() -> Self {
-        let mut transformer = Transformer::default();
-        transformer.register_parameter(TransformerParameterFn::Value(Box::new(|value| {
-            optimize_color(&value).into()
-        })));
-        transformer.register_parameter(TransformerParameterFn::Value(Box::new(|mut value| {
-            if value.starts_with("0px") {
-                value = format!("0{}", value.trim_start_matches("0px"))
-            }
-            if value.starts_with("0rem") {
-                value = format!("0{}", value.trim_start_matches("0rem"))
-            }
-            if value.starts_with("0.") {
-                value = format!(".{}", value.trim_start_matches("0."))
-            }
-            value
-                .replace(" 0px", " 0")
-                .replace(" 0rem", " 0")
-                .replace(" 0.", " .")
-                .replace(", ", ",")
-                .replace(" !important", "!important")
-        })));
-
-        transformer.register_parameter(TransformerParameterFn::Name(Box::new(|name| {
-            name.to_lowercase()
-        })));
-
-        let merge_m_n_p = Merge;
-        let merge_shorthand = MergeShortHand;
-        let media = MergeMedia;
-        let blocks = MergeBlocks;
-        let font = FontTransformer;
-
-        Minifier {
-            merge_m_n_p,
-            merge_shorthand,
-            transformer,
-            media,
-            blocks,
-            font,
-        }
+        MinifierBuilder::default().build()
     }
 }
 
@@ -118,7 +133,8 @@ pub enum Level {
     /// Level One + shortcuts (margins, paddings, backgrounds and etc)
     /// In mostly cases it's non dangerous optimizations, but be careful
     Two = 2,
-    /// Level Two + merge @media and css blocks with equal screen/selectors
+    /// Level Two + merge @media, @container and css blocks with equal screen/selectors,
+    /// merge css blocks with equal declarations
     /// It is a danger optimizations, because ordering of your css code may be changed
     Three = 3,
 }
@@ -165,42 +181,164 @@ impl Debug for ParseLevelError {
 
 impl Error for ParseLevelError {}
 
-pub type MResult<'a> = Result<String, MError<'a>>;
+pub type MResult = Result<String, MError>;
+
+/// Parse error with the position of the invalid part of the input
+#[derive(Clone, PartialEq, Eq)]
+pub struct MError {
+    /// Byte offset in the input
+    pub offset: usize,
+    /// Line number, starting from 1
+    pub line: usize,
+    /// Column in characters, starting from 1
+    pub column: usize,
+    /// Entity which was parsed: `block`, `@media`, `@import` and etc
+    pub kind: String,
+    /// What was expected at the position
+    pub expected: String,
+    source_line: String,
+}
+
+impl MError {
+    pub(crate) fn new(input: &str, error: nom::Err<nom::error::Error<&str>>) -> Self {
+        Self::at_entity(input, failed_entity(input, &error))
+    }
+
+    /// Error of the entity starting at the given offset
+    pub(crate) fn at_entity(input: &str, entity: usize) -> Self {
+        let rest = &input[entity..];
+        let offset = entity
+            + entity_error(rest)
+                .map(|error| rest.len() - error.len())
+                .unwrap_or_default();
+
+        let kind = match rest.strip_prefix('@') {
+            Some(at) => format!(
+                "@{}",
+                at.split(|c: char| !c.is_alphanumeric() && c != '-')
+                    .next()
+                    .unwrap_or_default()
+            ),
+            None => "block".to_string(),
+        };
+        let expected = if offset == input.len() && kind.starts_with('@') && !rest.contains('{') {
+            "`;`"
+        } else if offset == input.len() {
+            "`}`"
+        } else if offset == entity {
+            "selector or at-rule"
+        } else if input[entity..offset].contains('{') {
+            "declaration or `}`"
+        } else {
+            "`{`"
+        };
+
+        MError {
+            offset,
+            line: 0,
+            column: 0,
+            kind,
+            expected: expected.to_string(),
+            source_line: String::new(),
+        }
+        .locate(input, offset)
+    }
+
+    /// Moves the error to the given offset of the input
+    pub(crate) fn locate(self, input: &str, offset: usize) -> Self {
+        self.locate_from(input, offset, (0, 1))
+    }
+
+    /// Moves the error to the given offset of the input, lines are counted
+    /// from the `known` offset with its line number
+    pub(crate) fn locate_from(mut self, input: &str, offset: usize, known: (usize, usize)) -> Self {
+        let line_start = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = input[offset..]
+            .find('\n')
+            .map_or(input.len(), |i| offset + i);
+        self.offset = offset;
+        self.line = input[known.0..offset].matches('\n').count() + known.1;
+        self.column = input[line_start..offset].chars().count() + 1;
+        self.source_line = input[line_start..line_end]
+            .trim_end_matches('\r')
+            .to_string();
+        self
+    }
+
+    /// Renders the invalid line with a caret under the error position
+    ///
+    /// ```text
+    ///   5 | a { color: red; }}
+    ///     |                  ^ expected selector or at-rule
+    /// ```
+    pub fn snippet(&self) -> String {
+        let number = self.line.to_string();
+        let padding = " ".repeat(number.len());
+        let caret = self
+            .source_line
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        format!(
+            "{} | {}\n{} | {}^ expected {}",
+            number, self.source_line, padding, caret, self.expected
+        )
+    }
+}
 
-#[derive(From, Into, PartialEq)]
-pub struct MError<'a>(&'a str, nom::Err<nom::error::Error<&'a str>>);
+/// Offset of the top level entity which failed to parse
+pub(crate) fn failed_entity(input: &str, error: &nom::Err<nom::error::Error<&str>>) -> usize {
+    match error {
+        Err::Error(e) | Err::Failure(e) => input.len() - e.input.len(),
+        Err::Incomplete(_) => input.len(),
+    }
+}
 
-impl Debug for MError<'_> {
+impl Debug for MError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("MError")
             .field("message", &format!("{}", self))
-            .field("error", &format!("{:?}", self.0))
+            .field("offset", &self.offset)
+            .field("expected", &self.expected)
             .finish()
     }
 }
 
-impl Display for MError<'_> {
+impl Display for MError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let unparsed_size = match &self.1 {
-            Err::Incomplete(n) => match n {
-                Needed::Unknown => None,
-                Needed::Size(s) => Some(s.get()),
-            },
-            Err::Error(e) => Some(e.input.len()),
-            Err::Failure(e) => Some(e.input.len()),
-        };
-        if let Some(size) = unparsed_size {
-            let parsed_css = &self.0[..size];
-            let (count, last_line) = parsed_css.lines().enumerate().last().unwrap();
+        write!(
+            f,
+            "Invalid {} at line {}:{}, expected {}",
+            self.kind, self.line, self.column, self.expected
+        )
+    }
+}
 
-            write!(f, "Invalid block at line {}:{}", count + 1, last_line.len())
-        } else {
-            write!(f, "Invalid css")
-        }
+impl Error for MError {}
+
+/// Replaces every match of the pattern, the borrowed value is kept when nothing matches
+pub(crate) fn replace<'a>(value: Cow<'a, str>, from: &str, to: &str) -> Cow<'a, str> {
+    match value.contains(from) {
+        true => value.replace(from, to).into(),
+        false => value,
+    }
+}
+
+/// Lowercase value, the borrowed value is kept when it is lowercase already
+pub(crate) fn lowercase(value: Cow<'_, str>) -> Cow<'_, str> {
+    match value.chars().any(char::is_uppercase) {
+        true => value.to_lowercase().into(),
+        false => value,
     }
 }
 
-impl<'a> Error for MError<'a> {}
+/// Checks that some of the given declarations is repeated,
+/// so the earlier one may be a fallback for older browsers
+#[inline]
+pub(crate) fn has_fallbacks(parameters: &Parameters, names: &[&str]) -> bool {
+    names.iter().any(|name| parameters.count(name) > 1)
+}
 
 #[inline]
 pub(crate) fn if_some_has_important(input: Option<&Value>) -> bool {
@@ -242,7 +380,7 @@ mod test {
             "#,
                 Level::Three,
             ),
-            Ok("#some_id,input{padding:5px 3px;color:white}#some_id_2,.class{padding:5px 4px;color:#fff;font-weight:700}".into())
+            Ok("#some_id,input{padding:5px 3px;color:#fff}#some_id_2,.class{padding:5px 4px;color:#fff;font-weight:700}".into())
         )
     }
 
@@ -268,10 +406,71 @@ mod test {
                 )
                 .unwrap_err()
                 .to_string(),
-            "Invalid block at line 9:28"
+            "Invalid block at line 5:18, expected selector or at-rule"
         )
     }
 
+    #[test]
+    fn test_minify_invalid_declaration() {
+        let error = Minifier::default()
+            .minify("a { color: red }\nb {\n  color: ;; }\n", Level::One)
+            .unwrap_err();
+        assert_eq!((error.offset, error.line, error.column), (23, 3, 3));
+        assert_eq!(
+            error.to_string(),
+            "Invalid block at line 3:3, expected declaration or `}`"
+        );
+        assert_eq!(
+            error.snippet(),
+            "3 |   color: ;; }\n  |   ^ expected declaration or `}`"
+        )
+    }
+
+    #[test]
+    fn test_minify_tolerant() {
+        let (css, diagnostics) = Minifier::default().minify_tolerant(
+            r#"
+            a { color: red;; margin: 0px; }
+            b { color: ; padding: 1px }
+            } c { color: green }
+            @media print { d!! { color: black } e { color: white } }
+            f { color: blue
+            "#,
+            Level::One,
+        );
+        assert_eq!(
+            css,
+            "a{color:red;margin:0}b{padding:1px}@media print{e{color:#fff}}f{color:blue}"
+        );
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|e| (e.line, e.column))
+                .collect::<Vec<_>>(),
+            vec![(3, 17), (4, 13), (5, 28), (6, 17)]
+        );
+    }
+
+    #[test]
+    fn test_minify_tolerant_eof() {
+        let minify = |input| Minifier::default().minify_tolerant(input, Level::One);
+        assert_eq!(minify("a { content: \"abc").0, "a{content:\"abc\"}");
+        assert_eq!(minify("a { b: url(x.png").0, "a{b:url(x.png)}");
+        assert_eq!(minify("a { b { c: rgb(1, 2").0, "a{b{c:rgb(1,2)}}");
+        assert_eq!(minify("a { b: ( }").0, "a{}");
+        assert_eq!(minify("@media x '{").0, "");
+        assert_eq!(minify("@media0pxx \n'[ {@supports)").0, "");
+        let (css, diagnostics) = minify("@media print { b { color: ");
+        assert_eq!(css, "@media print{}");
+        assert_eq!(diagnostics.len(), 1);
+        let (css, diagnostics) = minify("a {} @import 'x'");
+        assert_eq!(css, "a{}@import 'x';");
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "Invalid @import at line 1:17, expected `;`"
+        );
+    }
+
     #[test]
     fn test_block_with_modificator_selector() {
         assert_eq!(
@@ -302,6 +501,105 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_fallbacks() {
+        assert_eq!(
+            Minifier::default().minify(
+                ".box{display:-webkit-box;display:flex;background:#fff;background:linear-gradient(#fff, #000);color:#000;color:#000}",
+                Level::Two,
+            ),
+            Ok(".box{display:-webkit-box;display:flex;background:#fff;background:linear-gradient(#fff,#000);color:#000}".into())
+        )
+    }
+
+    #[test]
+    fn test_layers() {
+        assert_eq!(
+            Minifier::default().minify(
+                r#"
+                @layer reset, base;
+                a { color: red; }
+                @layer base {
+                    a { margin: 0; }
+                    @media print { a { color: black; } }
+                }
+                @media print { a { padding: 0; } }
+                a { color: blue; }
+                "#,
+                Level::Three,
+            ),
+            Ok("@layer reset,base;a{color:red;color:blue}@layer base{a{margin:0}@media print{a{color:#000}}}@media print{a{padding:0}}".into())
+        )
+    }
+
+    #[test]
+    fn test_containers() {
+        assert_eq!(
+            Minifier::default().minify(
+                r#"
+                @container card (min-width: 400px) { .title { font-size: 2em; } }
+                .card { container: card / inline-size; }
+                @container card (min-width: 400px) { .text { font-size: 1em; } }
+                "#,
+                Level::Three,
+            ),
+            Ok("@container card (min-width:400px){.title{font-size:2em}.text{font-size:1em}}.card{container:card / inline-size}".into())
+        )
+    }
+
+    #[test]
+    fn test_nesting() {
+        let input = r#"
+            .card {
+                color: red;
+                &:hover { color: blue; }
+                @media print { padding: 0px; }
+            }
+            "#;
+        assert_eq!(
+            Minifier::default().minify(input, Level::One),
+            Ok(".card{color:red;&:hover{color:blue}@media print{padding:0}}".into())
+        );
+        assert_eq!(
+            Minifier::default()
+                .flatten_nesting(true)
+                .minify(input, Level::One),
+            Ok(".card{color:red}.card:hover{color:blue}@media print{.card{padding:0}}".into())
+        );
+        assert_eq!(
+            Minifier::default().flatten_nesting(true).minify(
+                ".a { color: red; & { color: blue } color: green }",
+                Level::One
+            ),
+            Ok(".a{color:red}.a{color:blue}.a{color:green}".into())
+        )
+    }
+
+    #[test]
+    fn test_selectors_order() {
+        let input = "b, a { color: red } a, b { margin: 0 }";
+        assert_eq!(
+            Minifier::default().minify(input, Level::Zero),
+            Ok("b,a{color:red}a,b{margin:0}".into())
+        );
+        assert_eq!(
+            Minifier::default().minify(input, Level::Three),
+            Ok("b,a{color:red;margin:0}".into())
+        );
+        assert_eq!(
+            Minifier::default()
+                .sort_selectors(true)
+                .minify(input, Level::Zero),
+            Ok("a,b{color:red}a,b{margin:0}".into())
+        );
+        assert_eq!(
+            Minifier::default()
+                .sort_selectors(true)
+                .minify("B, a { color: red }", Level::One),
+            Ok("a,b{color:red}".into())
+        )
+    }
+
     #[test]
     fn test_block_with_invalid_color() {
         assert_eq!(
@@ -309,4 +607,22 @@ mod test {
             Ok("*{color:#f}".into())
         )
     }
+
+    #[test]
+    fn test_nested_functions() {
+        assert_eq!(
+            Minifier::default().minify(
+                "a { background: linear-gradient(#FF0000, rgba(0,0,0,0)) }",
+                Level::One
+            ),
+            Ok("a{background:linear-gradient(red,transparent)}".into())
+        );
+        assert_eq!(
+            Minifier::default().minify(
+                "a { background: url(a), linear-gradient(red, rgba(0,0,0,.5)) }",
+                Level::One
+            ),
+            Ok("a{background:url(a),linear-gradient(red,rgba(0,0,0,.5))}".into())
+        );
+    }
 }
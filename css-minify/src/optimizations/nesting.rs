@@ -0,0 +1,277 @@
+use crate::optimizations::transformer::Transform;
+use crate::structure::{
+    Block, Combinator, CombinatorKind, ComplexSelector, CompoundSelector, Container, CssEntities,
+    CssEntity, Layer, Media, PseudoArgument, PseudoClass, Selector, Selectors, Supports,
+};
+
+/// Rewrites nested rules into plain rules for browsers without css nesting support
+#[derive(Default, Debug, Clone)]
+pub struct FlattenNesting;
+
+impl Transform for FlattenNesting {
+    fn transform<'a>(&self, entity: CssEntity<'a>) -> CssEntity<'a> {
+        entity
+    }
+
+    fn transform_many<'a>(&self, entities: CssEntities<'a>) -> CssEntities<'a> {
+        flatten(entities, None).into()
+    }
+}
+
+fn flatten<'a>(entities: CssEntities<'a>, parent: Option<&Selectors<'a>>) -> Vec<CssEntity<'a>> {
+    entities
+        .0
+        .into_iter()
+        .flat_map(|entity| match entity {
+            CssEntity::Block(block) => {
+                let selectors = match parent {
+                    Some(parent) if block.is_bare() => parent.clone(),
+                    Some(parent) => resolve(parent, &block.selectors),
+                    None => block.selectors,
+                };
+                let mut flat = vec![];
+                if !block.parameters.is_empty() || block.entities.is_empty() {
+                    flat.push(
+                        Block {
+                            selectors: selectors.clone(),
+                            parameters: block.parameters,
+                            entities: Default::default(),
+                            location: block.location,
+                        }
+                        .into(),
+                    );
+                }
+                flat.append(&mut flatten(block.entities, Some(&selectors)));
+                flat
+            }
+            CssEntity::Media(Media { screen, entities }) => vec![Media {
+                screen,
+                entities: flatten(entities, parent).into(),
+            }
+            .into()],
+            CssEntity::Container(Container {
+                name,
+                condition,
+                entities,
+            }) => vec![Container {
+                name,
+                condition,
+                entities: flatten(entities, parent).into(),
+            }
+            .into()],
+            CssEntity::Supports(Supports {
+                conditions,
+                entities,
+            }) => vec![Supports {
+                conditions,
+                entities: flatten(entities, parent).into(),
+            }
+            .into()],
+            CssEntity::Layer(Layer { names, entities }) => vec![Layer {
+                names,
+                entities: entities.map(|entities| flatten(entities, parent).into()),
+            }
+            .into()],
+            entity => vec![entity],
+        })
+        .collect()
+}
+
+/// Replaces `&` with every parent selector, selectors without `&` are descendants of the parent
+fn resolve<'a>(parent: &Selectors<'a>, nested: &Selectors<'a>) -> Selectors<'a> {
+    nested
+        .iter()
+        .flat_map(|nested| {
+            parent
+                .iter()
+                .map(move |parent| resolve_complex(parent, nested))
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+fn resolve_complex<'a>(
+    parent: &ComplexSelector<'a>,
+    nested: &ComplexSelector<'a>,
+) -> ComplexSelector<'a> {
+    if !contains_nesting(nested) {
+        let mut resolved = parent.clone();
+        resolved.extend(
+            nested
+                .iter()
+                .enumerate()
+                .map(|(i, (combinator, compound))| {
+                    let combinator = match combinator {
+                        None if i == 0 => Some(Combinator {
+                            kind: CombinatorKind::Descendant,
+                            spaced: true,
+                        }),
+                        combinator => *combinator,
+                    };
+                    (combinator, compound.clone())
+                }),
+        );
+        return resolved;
+    }
+
+    let mut resolved = ComplexSelector::default();
+    for (combinator, compound) in nested.iter() {
+        if !compound.contains(&Selector::Nesting) {
+            resolved.push((*combinator, resolve_compound(parent, compound)));
+            continue;
+        }
+        // `&` matches the last compound of the parent, so the rest of the compound is merged into it.
+        // It's only the same selector when the ancestors of the parent stay before the nested ones
+        // and the merged compound doesn't get two type selectors, otherwise `&` is `:is(parent)`
+        let (last, ancestors) = parent.split_last().expect("selector is never empty");
+        if (!ancestors.is_empty() && !resolved.is_empty())
+            || (has_tag(&last.1) && has_tag(compound))
+        {
+            let is_parent = Selector::PseudoClass(PseudoClass {
+                name: "is".into(),
+                argument: Some(PseudoArgument::Selectors(vec![parent.clone()].into())),
+            });
+            let compound = resolve_compound(parent, compound)
+                .0
+                .into_iter()
+                .map(|selector| match selector {
+                    Selector::Nesting => is_parent.clone(),
+                    selector => selector,
+                })
+                .collect::<Vec<_>>();
+            resolved.push((*combinator, tag_first(compound)));
+            continue;
+        }
+        let mut ancestors = ancestors.to_vec();
+        let mut last_combinator = last.0;
+        match ancestors.first_mut() {
+            Some((first, _)) => *first = combinator.or(*first),
+            None => last_combinator = combinator.or(last_combinator),
+        }
+        resolved.extend(ancestors);
+        let mut merged = vec![];
+        for selector in resolve_compound(parent, compound).0 {
+            match selector {
+                Selector::Nesting => merged.extend(last.1.iter().cloned()),
+                selector => merged.push(selector),
+            }
+        }
+        resolved.push((last_combinator, tag_first(merged)));
+    }
+    resolved
+}
+
+fn has_tag(compound: &CompoundSelector) -> bool {
+    compound.iter().any(|s| matches!(s, Selector::Tag(_)))
+}
+
+/// The type selector must be the first one of the compound: `div.a`, not `.adiv`
+fn tag_first(compound: Vec<Selector>) -> CompoundSelector {
+    let (mut tags, rest): (Vec<_>, Vec<_>) = compound
+        .into_iter()
+        .partition(|s| matches!(s, Selector::Tag(_)));
+    tags.extend(rest);
+    tags.into()
+}
+
+/// Resolves `&` inside of the selector list arguments: `:not(&)`
+fn resolve_compound<'a>(
+    parent: &ComplexSelector<'a>,
+    compound: &CompoundSelector<'a>,
+) -> CompoundSelector<'a> {
+    compound
+        .iter()
+        .map(|selector| match selector {
+            Selector::PseudoClass(PseudoClass {
+                name,
+                argument: Some(PseudoArgument::Selectors(selectors)),
+            }) => Selector::PseudoClass(PseudoClass {
+                name: name.clone(),
+                argument: Some(PseudoArgument::Selectors(
+                    selectors
+                        .iter()
+                        .map(|nested| {
+                            if contains_nesting(nested) {
+                                resolve_complex(parent, nested)
+                            } else {
+                                nested.clone()
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .into(),
+                )),
+            }),
+            selector => selector.clone(),
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+fn contains_nesting(selector: &ComplexSelector) -> bool {
+    selector.iter().any(|(_, compound)| {
+        compound.iter().any(|selector| match selector {
+            Selector::Nesting => true,
+            Selector::PseudoClass(PseudoClass {
+                argument: Some(PseudoArgument::Selectors(selectors)),
+                ..
+            }) => selectors.iter().any(contains_nesting),
+            _ => false,
+        })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::optimizations::nesting::FlattenNesting;
+    use crate::optimizations::transformer::Transform;
+    use crate::parse;
+
+    #[test]
+    fn test_flatten() {
+        assert_eq!(
+            FlattenNesting
+                .transform_many(
+                    parse(
+                        r#"
+                        .card, .panel {
+                            color: red;
+                            &:hover { color: blue; }
+                            .title { font-weight: bold; }
+                            > p { margin: 0; }
+                            @media (min-width: 400px) {
+                                padding: 0;
+                                .title & { color: green; }
+                            }
+                        }
+                        "#
+                    )
+                    .unwrap()
+                )
+                .to_string(),
+            ".card,.panel{color:red}.card:hover,.panel:hover{color:blue}.card .title,.panel .title{font-weight:bold}.card > p,.panel > p{margin:0}@media (min-width: 400px){.card,.panel{padding:0}.title .card,.title .panel{color:green}}"
+        )
+    }
+
+    #[test]
+    fn test_flatten_compound() {
+        let flatten = |input| {
+            FlattenNesting
+                .transform_many(parse(input).unwrap())
+                .to_string()
+        };
+        assert_eq!(flatten(".b{&div{color:red}}"), "div.b{color:red}");
+        assert_eq!(flatten("p{div&{color:red}}"), "div:is(p){color:red}");
+        assert_eq!(
+            flatten(".a .b{&:hover{color:red}}"),
+            ".a .b:hover{color:red}"
+        );
+        assert_eq!(
+            flatten(".a .b{.x &{color:red}}"),
+            ".x :is(.a .b){color:red}"
+        );
+        assert_eq!(
+            flatten(".a>.b{& + &{color:red}}"),
+            ".a>.b + :is(.a>.b){color:red}"
+        );
+    }
+}
@@ -0,0 +1,251 @@
+/// Units of the same kind with their size as a fraction of the first one,
+/// earlier units are preferred when conversions are equally short
+const UNITS: [&[(&str, u128, u128)]; 4] = [
+    &[("s", 1, 1), ("ms", 1, 1000)],
+    &[("khz", 1, 1), ("hz", 1, 1000)],
+    &[("turn", 1, 1), ("deg", 1, 360), ("grad", 1, 400)],
+    &[
+        ("in", 1, 1),
+        ("px", 1, 96),
+        ("cm", 50, 127),
+        ("mm", 5, 127),
+        ("pt", 1, 72),
+        ("pc", 1, 6),
+    ],
+];
+
+const LENGTH_UNITS: [&str; 25] = [
+    "px", "em", "rem", "ex", "rex", "ch", "rch", "cap", "rcap", "ic", "ric", "lh", "rlh", "cm",
+    "mm", "q", "in", "pt", "pc", "cqw", "cqh", "cqi", "cqb", "cqmin", "cqmax",
+];
+
+/// Longest exponent which is written out in the plain form
+const MAX_EXPONENT: i32 = 64;
+
+/// Exact decimal number: ± mantissa × 10^exponent
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub(crate) struct Decimal {
+    negative: bool,
+    mantissa: u128,
+    exponent: i32,
+}
+
+impl Decimal {
+    /// Number like `-1.50e3`, `None` when it doesn't fit
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(text) => (true, text),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (digits, exponent) = match text.find(['e', 'E']) {
+            Some(i) => (&text[..i], text[i + 1..].parse::<i32>().ok()?),
+            None => (text, 0),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let mut mantissa = 0u128;
+        for c in integer.chars().chain(fraction.chars()) {
+            mantissa = mantissa
+                .checked_mul(10)?
+                .checked_add(c.to_digit(10)? as u128)?;
+        }
+        let exponent = exponent.checked_sub(fraction.len() as i32)?;
+        Some(
+            Decimal {
+                negative,
+                mantissa,
+                exponent,
+            }
+            .normalize(),
+        )
+    }
+
+    pub(crate) fn is_zero(&self) -> bool {
+        self.mantissa == 0
+    }
+
+    fn normalize(mut self) -> Self {
+        if self.mantissa == 0 {
+            return Decimal {
+                negative: false,
+                mantissa: 0,
+                exponent: 0,
+            };
+        }
+        while self.mantissa % 10 == 0 {
+            self.mantissa /= 10;
+            self.exponent += 1;
+        }
+        self
+    }
+
+    /// Multiplied by `numerator / denominator` when the result is a finite decimal
+    fn scale(self, numerator: u128, denominator: u128) -> Option<Self> {
+        let mut mantissa = self.mantissa.checked_mul(numerator)?;
+        let mut exponent = self.exponent;
+        while mantissa % denominator != 0 {
+            mantissa = mantissa.checked_mul(10)?;
+            exponent = exponent.checked_sub(1)?;
+        }
+        Some(
+            Decimal {
+                negative: self.negative,
+                mantissa: mantissa / denominator,
+                exponent,
+            }
+            .normalize(),
+        )
+    }
+
+    /// Shortest text of the number, the exponent is used only when it is allowed
+    fn format(&self, plus: bool, scientific: bool) -> Option<String> {
+        let sign = match (self.negative, plus) {
+            (true, _) => "-",
+            (false, true) => "+",
+            (false, false) => "",
+        };
+        let digits = self.mantissa.to_string();
+        let plain = match self.exponent {
+            e if e.abs() > MAX_EXPONENT => None,
+            e if e >= 0 => Some(format!("{}{}", digits, "0".repeat(e as usize))),
+            e if digits.len() > e.unsigned_abs() as usize => {
+                let (integer, fraction) = digits.split_at(digits.len() - e.unsigned_abs() as usize);
+                Some(format!("{}.{}", integer, fraction))
+            }
+            e => Some(format!(
+                ".{}{}",
+                "0".repeat(e.unsigned_abs() as usize - digits.len()),
+                digits
+            )),
+        };
+        let exponential =
+            (scientific && self.exponent != 0).then(|| format!("{}e{}", digits, self.exponent));
+        [plain, exponential]
+            .into_iter()
+            .flatten()
+            .min_by_key(String::len)
+            .map(|number| format!("{}{}", sign, number))
+    }
+}
+
+/// Shortest text of the number, the exponent form is used only when the number already has it
+pub(crate) fn minify_number(text: &str) -> String {
+    Decimal::parse(text)
+        .and_then(|number| number.format(text.starts_with('+'), text.contains(['e', 'E'])))
+        .filter(|minified| minified.len() <= text.len())
+        .unwrap_or_else(|| text.to_string())
+}
+
+/// Shortest text of the dimension, it may be converted to an equal unit of the same kind
+pub(crate) fn minify_dimension(number: &str, unit: &str) -> (String, String) {
+    let minified = (minify_number(number), unit.to_string());
+    let Some(decimal) = Decimal::parse(number) else {
+        return minified;
+    };
+    let lowercase = unit.to_ascii_lowercase();
+    let Some(units) = UNITS
+        .iter()
+        .find(|units| units.iter().any(|(u, _, _)| *u == lowercase))
+    else {
+        return minified;
+    };
+    let (_, from_numerator, from_denominator) =
+        units.iter().find(|(u, _, _)| *u == lowercase).unwrap();
+    let plus = number.starts_with('+');
+    let scientific = number.contains(['e', 'E']);
+    units
+        .iter()
+        .filter(|(u, _, _)| *u != lowercase)
+        .filter_map(|(u, numerator, denominator)| {
+            let (n, d) = reduce(from_numerator * denominator, from_denominator * numerator);
+            let converted = decimal.scale(n, d)?.format(plus, scientific)?;
+            Some((converted, u.to_string()))
+        })
+        .fold(minified, |shortest, candidate| {
+            match candidate.0.len() + candidate.1.len() < shortest.0.len() + shortest.1.len() {
+                true => candidate,
+                false => shortest,
+            }
+        })
+}
+
+/// Unit of the length which may be dropped on zero
+pub(crate) fn is_length(unit: &str) -> bool {
+    let unit = unit.to_ascii_lowercase();
+    let viewport = unit
+        .trim_start_matches(['s', 'l', 'd'])
+        .strip_prefix('v')
+        .is_some_and(|u| ["w", "h", "i", "b", "min", "max"].contains(&u));
+    viewport || LENGTH_UNITS.contains(&unit.as_str())
+}
+
+fn reduce(numerator: u128, denominator: u128) -> (u128, u128) {
+    let (mut a, mut b) = (numerator, denominator);
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    (numerator / a, denominator / a)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::optimizations::numbers::{is_length, minify_dimension, minify_number};
+
+    #[test]
+    fn test_minify_number() {
+        for (number, expected) in [
+            ("1.50", "1.5"),
+            ("0.50", ".5"),
+            ("-0.50", "-.5"),
+            ("+0.5", "+.5"),
+            ("-0", "0"),
+            ("0.0", "0"),
+            ("010", "10"),
+            ("1e3", "1e3"),
+            ("1.5e3", "1500"),
+            ("1000e-3", "1"),
+            ("1E-7", "1e-7"),
+            ("1000000", "1000000"),
+            ("1e99999999999", "1e99999999999"),
+            (
+                "123456789012345678901234567890123456789012",
+                "123456789012345678901234567890123456789012",
+            ),
+        ] {
+            assert_eq!(minify_number(number), expected, "{}", number);
+        }
+    }
+
+    #[test]
+    fn test_minify_dimension() {
+        for (number, unit, expected) in [
+            ("1.50", "em", "1.5em"),
+            ("500", "ms", ".5s"),
+            ("0.5", "s", ".5s"),
+            ("1500", "MS", "1.5s"),
+            ("100", "ms", ".1s"),
+            ("10", "mm", "1cm"),
+            ("96", "px", "1in"),
+            ("12", "pt", "1pc"),
+            ("48", "pt", "4pc"),
+            (".25", "in", "24px"),
+            ("15", "px", "15px"),
+            ("180", "deg", "180deg"),
+            ("360", "deg", "1turn"),
+            ("-1000", "Hz", "-1khz"),
+            ("0", "s", "0s"),
+        ] {
+            let (number, unit) = minify_dimension(number, unit);
+            assert_eq!(format!("{}{}", number, unit), expected);
+        }
+    }
+
+    #[test]
+    fn test_is_length() {
+        assert!(is_length("PX"));
+        assert!(is_length("svh"));
+        assert!(is_length("vmin"));
+        assert!(!is_length("s"));
+        assert!(!is_length("deg"));
+        assert!(!is_length("fr"));
+    }
+}
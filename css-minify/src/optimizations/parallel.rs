@@ -0,0 +1,132 @@
+use crate::optimizations::builder::Stage;
+use crate::optimizations::{Level, MResult, Minifier};
+use crate::structure::{CssEntities, Stylesheet};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Minimal count of top level rules transformed by a thread, smaller stylesheets
+/// are not worth spawning threads
+const MIN_CHUNK: usize = 256;
+
+impl Minifier {
+    /// Minify every input on a pool of threads, one per available cpu.
+    /// Results are returned in the order of the inputs
+    ///
+    /// ```rust
+    /// use css_minify::optimizations::{Level, Minifier};
+    /// let results = Minifier::default().minify_batch(&["a { color: #FFFFFF }", "b {"], Level::One);
+    /// assert_eq!(results[0], Ok("a{color:#fff}".into()));
+    /// assert!(results[1].is_err());
+    /// ```
+    pub fn minify_batch<S: AsRef<str> + Sync>(&self, inputs: &[S], level: Level) -> Vec<MResult> {
+        let threads = thread::available_parallelism()
+            .map(NonZeroUsize::get)
+            .unwrap_or(1)
+            .min(inputs.len());
+        let next = AtomicUsize::new(0);
+        let mut results = thread::scope(|scope| {
+            let workers = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut results = vec![];
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            match inputs.get(i) {
+                                Some(input) => {
+                                    results.push((i, self.minify(input.as_ref(), level)))
+                                }
+                                None => return results,
+                            }
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+            workers.into_iter().flat_map(join).collect::<Vec<_>>()
+        });
+        results.sort_unstable_by_key(|(i, _)| *i);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+}
+
+pub(crate) fn transform_stages<'a>(
+    stages: &[&Stage],
+    stylesheet: Stylesheet<'a>,
+) -> Stylesheet<'a> {
+    stages.iter().fold(stylesheet, |stylesheet, stage| {
+        stage.transform.transform_many(stylesheet)
+    })
+}
+
+/// Splits top level rules into chunks which are transformed on their own threads,
+/// the order of rules is kept
+pub(crate) fn transform_parallel<'a>(
+    stages: &[&Stage],
+    stylesheet: Stylesheet<'a>,
+    threads: usize,
+) -> Stylesheet<'a> {
+    let threads = threads.min(stylesheet.len() / MIN_CHUNK);
+    if threads <= 1 {
+        return transform_stages(stages, stylesheet);
+    }
+    let size = stylesheet.len().div_ceil(threads);
+    let mut rest = stylesheet.0;
+    let mut chunks = Vec::with_capacity(threads);
+    while !rest.is_empty() {
+        let tail = rest.split_off(size.min(rest.len()));
+        chunks.push(std::mem::replace(&mut rest, tail));
+    }
+    thread::scope(|scope| {
+        let workers = chunks
+            .into_iter()
+            .map(|chunk| scope.spawn(move || transform_stages(stages, CssEntities(chunk)).0))
+            .collect::<Vec<_>>();
+        CssEntities(workers.into_iter().flat_map(join).collect())
+    })
+}
+
+/// Panics of the worker are resumed on the calling thread
+fn join<T>(worker: thread::ScopedJoinHandle<'_, T>) -> T {
+    worker
+        .join()
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::optimizations::{Level, Minifier};
+
+    #[test]
+    fn test_minify_batch() {
+        let inputs = (0..20)
+            .map(|i| format!(".a{} {{ color: #FFFFFF; margin: {}px 0px }}", i, i))
+            .chain(["a {".to_string()])
+            .collect::<Vec<_>>();
+        let minifier = Minifier::default();
+        let results = minifier.minify_batch(&inputs, Level::Two);
+        assert_eq!(results.len(), inputs.len());
+        for (input, result) in inputs.iter().zip(results) {
+            assert_eq!(result, minifier.minify(input, Level::Two));
+        }
+        assert!(minifier.minify_batch::<&str>(&[], Level::One).is_empty());
+    }
+
+    #[test]
+    fn test_parallel_optimize() {
+        let input = (0..2000)
+            .map(|i| {
+                format!(
+                    ".b{} {{ font-weight: bold; margin-top: 0px; margin-right: 1px; margin-bottom: 0px; margin-left: 1px }} .b{} {{ color: RED }}",
+                    i % 700,
+                    i % 3
+                )
+            })
+            .collect::<String>();
+        for level in [Level::One, Level::Two, Level::Three] {
+            assert_eq!(
+                Minifier::default().threads(4).minify(&input, level),
+                Minifier::default().minify(&input, level)
+            );
+        }
+    }
+}
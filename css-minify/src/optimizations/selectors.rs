@@ -0,0 +1,268 @@
+use crate::optimizations::lowercase;
+use crate::optimizations::transformer::Transform;
+use crate::parsers::selector::LEGACY_PSEUDO_ELEMENTS;
+use crate::structure::{
+    AttributeMatcher, Combinator, CombinatorKind, PseudoArgument, PseudoClass, Selector, Selectors,
+};
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+/// Removes whitespaces around combinators, quotes of identifier attribute values,
+/// lowercases type selectors and escapes, writes legacy pseudo elements with a single colon
+#[derive(Default, Debug, Clone)]
+pub(crate) struct SelectorTransformer;
+
+impl Transform for SelectorTransformer {
+    fn transform_selectors<'a>(&self, selectors: Selectors<'a>) -> Selectors<'a> {
+        rewrite(selectors, &unspace, &minify_selector)
+    }
+}
+
+/// Removes repeated selectors of the list. Selectors which differ only by whitespaces
+/// around combinators or by the case of html type selectors are the same
+#[derive(Default, Debug, Clone)]
+pub(crate) struct RemoveDuplicateSelectors;
+
+impl Transform for RemoveDuplicateSelectors {
+    fn transform_selectors<'a>(&self, selectors: Selectors<'a>) -> Selectors<'a> {
+        dedup_selectors(rewrite(selectors, &|c| c, &dedup_arguments))
+    }
+}
+
+fn dedup_selectors(selectors: Selectors) -> Selectors {
+    let mut seen = HashSet::new();
+    selectors
+        .0
+        .into_iter()
+        .filter(|selector| {
+            let key = rewrite(
+                vec![selector.clone()].into(),
+                &unspace,
+                &|selector| match selector {
+                    Selector::Tag(tag) => Selector::Tag(minify_tag(tag)),
+                    selector => selector,
+                },
+            );
+            seen.insert(key.to_string())
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+fn dedup_arguments(selector: Selector) -> Selector {
+    match selector {
+        Selector::PseudoClass(PseudoClass {
+            name,
+            argument: Some(PseudoArgument::Selectors(selectors)),
+        }) => Selector::PseudoClass(PseudoClass {
+            name,
+            argument: Some(dedup_selectors(selectors).into()),
+        }),
+        selector => selector,
+    }
+}
+
+/// Sorts selectors of the list, so equal lists are written the same way,
+/// which helps gzip and merging of blocks
+#[derive(Default, Debug, Clone)]
+pub(crate) struct SortSelectors;
+
+impl Transform for SortSelectors {
+    fn transform_selectors<'a>(&self, selectors: Selectors<'a>) -> Selectors<'a> {
+        sort_selectors(rewrite(selectors, &|c| c, &sort_arguments))
+    }
+}
+
+pub(crate) fn sort_selectors(mut selectors: Selectors) -> Selectors {
+    selectors.sort_by_cached_key(|selector| selector.to_string());
+    selectors
+}
+
+fn sort_arguments(selector: Selector) -> Selector {
+    match selector {
+        Selector::PseudoClass(PseudoClass {
+            name,
+            argument: Some(PseudoArgument::Selectors(selectors)),
+        }) => Selector::PseudoClass(PseudoClass {
+            name,
+            argument: Some(sort_selectors(selectors).into()),
+        }),
+        selector => selector,
+    }
+}
+
+/// Rewrites every combinator and simple selector, including selectors of the pseudo arguments
+fn rewrite<'a>(
+    selectors: Selectors<'a>,
+    combinator: &impl Fn(Combinator) -> Combinator,
+    simple: &impl Fn(Selector<'a>) -> Selector<'a>,
+) -> Selectors<'a> {
+    let argument = |argument: Option<PseudoArgument<'a>>| {
+        argument.map(|argument| match argument {
+            PseudoArgument::Selectors(selectors) => rewrite(selectors, combinator, simple).into(),
+            argument => argument,
+        })
+    };
+    selectors
+        .0
+        .into_iter()
+        .map(|selector| {
+            selector
+                .0
+                .into_iter()
+                .map(|(c, compound)| {
+                    let compound = compound
+                        .0
+                        .into_iter()
+                        .map(|selector| match selector {
+                            Selector::PseudoClass(mut pc) => {
+                                pc.argument = argument(pc.argument);
+                                Selector::PseudoClass(pc)
+                            }
+                            Selector::PseudoElement(mut pe) => {
+                                pe.argument = argument(pe.argument);
+                                Selector::PseudoElement(pe)
+                            }
+                            selector => selector,
+                        })
+                        .map(simple)
+                        .collect::<Vec<_>>()
+                        .into();
+                    (c.map(combinator), compound)
+                })
+                .collect::<Vec<_>>()
+                .into()
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Only the descendant combinator needs whitespace
+fn unspace(Combinator { kind, .. }: Combinator) -> Combinator {
+    Combinator {
+        kind,
+        spaced: kind == CombinatorKind::Descendant,
+    }
+}
+
+fn minify_selector(selector: Selector) -> Selector {
+    match selector {
+        Selector::Tag(tag) => Selector::Tag(minify_tag(tag)),
+        Selector::Id(id) => Selector::Id(lowercase_escapes(id)),
+        Selector::Class(class) => Selector::Class(lowercase_escapes(class)),
+        Selector::Attribute(mut attribute) => {
+            attribute.matcher = attribute.matcher.map(|mut matcher| {
+                if matcher.quote.is_some() && is_identifier(&matcher.value) {
+                    matcher.quote = None;
+                }
+                AttributeMatcher {
+                    flag: matcher.flag.map(|flag| flag.to_ascii_lowercase()),
+                    ..matcher
+                }
+            });
+            Selector::Attribute(attribute)
+        }
+        Selector::PseudoElement(mut pe) => {
+            pe.legacy |= LEGACY_PSEUDO_ELEMENTS.contains(&pe.name.to_lowercase().as_str());
+            Selector::PseudoElement(pe)
+        }
+        selector => selector,
+    }
+}
+
+/// Html element names are case-insensitive, but camelCase names may be svg elements
+/// (`foreignObject`), so only names without lowercase letters are lowercased
+fn minify_tag(tag: Cow<'_, str>) -> Cow<'_, str> {
+    if tag.chars().any(|c| c.is_lowercase()) {
+        lowercase_escapes(tag)
+    } else {
+        lowercase(tag)
+    }
+}
+
+/// `\3A` to `\3a`, other characters are case-sensitive
+fn lowercase_escapes(input: Cow<'_, str>) -> Cow<'_, str> {
+    if !input.contains('\\') {
+        return input;
+    }
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        output.push(c);
+        if c != '\\' {
+            continue;
+        }
+        let mut digits = 0;
+        while let Some(digit) = chars.next_if(|c| c.is_ascii_hexdigit() && digits < 6) {
+            output.push(digit.to_ascii_lowercase());
+            digits += 1;
+        }
+        if digits == 0 {
+            output.extend(chars.next());
+        }
+    }
+    output.into()
+}
+
+/// Value which may be written without quotes: `[type=submit]`
+fn is_identifier(value: &str) -> bool {
+    let name = value.strip_prefix('-').unwrap_or(value);
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_' || !c.is_ascii();
+    match name.chars().next() {
+        Some('-') => value.starts_with("--") && name.chars().all(is_name_char),
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || !c.is_ascii() => {
+            name.chars().all(is_name_char)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::optimizations::selectors::{
+        RemoveDuplicateSelectors, SelectorTransformer, SortSelectors,
+    };
+    use crate::optimizations::transformer::Transform;
+    use crate::parse;
+
+    #[test]
+    fn test_minify_selectors() {
+        assert_eq!(
+            SelectorTransformer
+                .transform_many(
+                    parse(
+                        r#"
+                        DIV > P ~ a + b c, foreignObject, .x\3A y, a::before, a::marker,
+                        [type="submit"], [lang='en-US' I], [data-x="a b"], [data-y="1"], [data-z="--a"],
+                        :not(a > b, ::after) { color: red }
+                        "#
+                    )
+                    .unwrap()
+                )
+                .to_string(),
+            r#"div>p~a+b c,foreignObject,.x\3a y,a:before,a::marker,[type=submit],[lang=en-US i],[data-x="a b"],[data-y="1"],[data-z=--a],:not(a>b,:after){color:red}"#
+        )
+    }
+
+    #[test]
+    fn test_remove_duplicate_selectors() {
+        assert_eq!(
+            RemoveDuplicateSelectors
+                .transform_many(
+                    parse("a, b, a, DIV > p, div>p, :is(i, I, b), foreignObject, foreignobject, FOREIGNOBJECT { color: red }").unwrap()
+                )
+                .to_string(),
+            "a,b,DIV > p,:is(i,b),foreignObject,foreignobject{color:red}"
+        )
+    }
+
+    #[test]
+    fn test_sort_selectors() {
+        assert_eq!(
+            SortSelectors
+                .transform_many(parse("b, a, :is(i, b) { color: red }").unwrap())
+                .to_string(),
+            ":is(b,i),a,b{color:red}"
+        )
+    }
+}
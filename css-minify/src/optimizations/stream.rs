@@ -0,0 +1,306 @@
+use crate::optimizations::{Level, MError, Minifier};
+use crate::parse;
+use crate::structure::{CssWriter, WriteCss};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::{self, BufWriter, ErrorKind, Read, Write};
+
+/// Size of the input read at once
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Error of [`Minifier::minify_to`]
+#[derive(Debug)]
+pub enum StreamError {
+    Io(io::Error),
+    Parse(MError),
+}
+
+impl Display for StreamError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StreamError::Io(e) => write!(f, "{}", e),
+            StreamError::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for StreamError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StreamError::Io(e) => Some(e),
+            StreamError::Parse(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for StreamError {
+    fn from(error: io::Error) -> Self {
+        StreamError::Io(error)
+    }
+}
+
+impl From<MError> for StreamError {
+    fn from(error: MError) -> Self {
+        StreamError::Parse(error)
+    }
+}
+
+impl Minifier {
+    /// Minify css from the reader into the writer. Top level rules are minified as soon
+    /// as they are read, unless the level enables passes which merge rules across
+    /// the stylesheet: then the whole input is read first.
+    ///
+    /// ```rust
+    /// use css_minify::optimizations::{Level, Minifier};
+    /// let mut output = vec![];
+    /// Minifier::default()
+    ///     .minify_to("a { color: #FFFFFF } b { margin: 0px }".as_bytes(), &mut output, Level::One)
+    ///     .unwrap();
+    /// assert_eq!(output, b"a{color:#fff}b{margin:0}");
+    /// ```
+    pub fn minify_to<R: Read, W: Write>(
+        &self,
+        mut reader: R,
+        writer: W,
+        level: Level,
+    ) -> Result<(), StreamError> {
+        let mut writer = IoWriter {
+            inner: BufWriter::new(writer),
+            error: None,
+        };
+        if !self.is_incremental(level) {
+            let mut input = String::new();
+            reader.read_to_string(&mut input)?;
+            writer.write(&self.optimize(parse(&input)?, level))?;
+            writer.inner.flush()?;
+            return Ok(());
+        }
+
+        let mut buffer = vec![];
+        let mut scanner = Scanner::default();
+        let mut position = Position::default();
+        loop {
+            let start = buffer.len();
+            buffer.resize(start + CHUNK_SIZE, 0);
+            let read = loop {
+                match reader.read(&mut buffer[start..]) {
+                    Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                    result => break result?,
+                }
+            };
+            buffer.truncate(start + read);
+            let end = match read {
+                0 => buffer.len(),
+                _ => scanner.scan(&buffer),
+            };
+            if end > 0 {
+                let chunk = std::str::from_utf8(&buffer[..end])
+                    .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+                let stylesheet = parse(chunk).map_err(|e| position.locate(e, chunk))?;
+                writer.write(&self.optimize(stylesheet, level))?;
+                position.advance(chunk);
+                buffer.drain(..end);
+                scanner.rebase(end);
+            }
+            if read == 0 {
+                break;
+            }
+        }
+        writer.inner.flush()?;
+        Ok(())
+    }
+
+    /// Every enabled pass changes top level rules one by one
+    fn is_incremental(&self, level: Level) -> bool {
+        self.stages
+            .iter()
+            .filter(|stage| stage.level <= level)
+            .all(|stage| stage.pass.is_some_and(|pass| !pass.is_cross_rule()))
+    }
+}
+
+/// Finds the end of the last complete top level rule in the read input
+#[derive(Default)]
+struct Scanner {
+    /// Bytes of the buffer which are already scanned
+    scanned: usize,
+    /// End of the last complete top level rule
+    boundary: usize,
+    depth: usize,
+    quote: Option<u8>,
+    comment: bool,
+    escape: bool,
+    previous: u8,
+    /// Last three bytes lowercased, to find `url(`
+    recent: [u8; 3],
+    /// Unquoted `url(` is open, `blank` while only whitespaces are read after it
+    url: bool,
+    blank: bool,
+}
+
+impl Scanner {
+    fn scan(&mut self, buffer: &[u8]) -> usize {
+        for (i, byte) in buffer.iter().enumerate().skip(self.scanned) {
+            let previous = std::mem::replace(&mut self.previous, *byte);
+            let recent = self.recent;
+            self.recent = [recent[1], recent[2], byte.to_ascii_lowercase()];
+            if self.escape {
+                self.escape = false;
+                self.previous = 0;
+                continue;
+            }
+            match (self.comment, self.quote, byte) {
+                (true, _, b'/') if previous == b'*' => self.comment = false,
+                (true, _, _) => {}
+                (false, _, b'\\') => self.escape = true,
+                (_, Some(quote), _) if quote == *byte => self.quote = None,
+                (_, Some(_), _) => {}
+                // `}` in the unquoted url doesn't close the block
+                (_, None, b')') if self.url => self.url = false,
+                (_, None, b'"' | b'\'') if self.url && self.blank => {
+                    self.url = false;
+                    self.quote = Some(*byte);
+                }
+                (_, None, _) if self.url => self.blank &= byte.is_ascii_whitespace(),
+                (_, None, b'"' | b'\'') => self.quote = Some(*byte),
+                (_, None, b'(') if &recent == b"url" => {
+                    self.url = true;
+                    self.blank = true;
+                }
+                (_, None, b'*') if previous == b'/' => {
+                    self.comment = true;
+                    // `/*/` doesn't close the comment
+                    self.previous = 0;
+                }
+                (_, None, b'{') => self.depth += 1,
+                (_, None, b'}') => {
+                    self.depth = self.depth.saturating_sub(1);
+                    if self.depth == 0 {
+                        self.boundary = i + 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.scanned = buffer.len();
+        self.boundary
+    }
+
+    /// The first `end` bytes are removed from the buffer
+    fn rebase(&mut self, end: usize) {
+        self.scanned = self.scanned.saturating_sub(end);
+        self.boundary = self.boundary.saturating_sub(end);
+    }
+}
+
+/// Position of the not yet minified input, to report errors like the whole input was parsed
+#[derive(Default)]
+struct Position {
+    offset: usize,
+    lines: usize,
+    /// Beginning of the current line
+    line: String,
+}
+
+impl Position {
+    fn locate(&self, error: MError, chunk: &str) -> MError {
+        let text = format!("{}{}", self.line, chunk);
+        let offset = self.line.len() + error.offset;
+        let mut error = error.locate(&text, offset);
+        error.offset += self.offset - self.line.len();
+        error.line += self.lines;
+        error
+    }
+
+    fn advance(&mut self, chunk: &str) {
+        self.offset += chunk.len();
+        match chunk.rfind('\n') {
+            Some(i) => {
+                self.lines += chunk.matches('\n').count();
+                self.line = chunk[i + 1..].to_string();
+            }
+            None => self.line.push_str(chunk),
+        }
+    }
+}
+
+/// Adapter which writes the formatted css into `io::Write`
+struct IoWriter<W: Write> {
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> IoWriter<W> {
+    fn write(&mut self, css: &impl WriteCss) -> io::Result<()> {
+        css.write_css(self).map_err(|_| {
+            self.error
+                .take()
+                .unwrap_or_else(|| io::Error::other("formatter error"))
+        })
+    }
+}
+
+impl<W: Write> std::fmt::Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            std::fmt::Error
+        })
+    }
+}
+
+impl<W: Write> CssWriter for IoWriter<W> {}
+
+#[cfg(test)]
+mod test {
+    use crate::optimizations::{Level, Minifier, StreamError};
+    use std::io::Read;
+
+    /// Reader which returns a few bytes at once
+    struct Trickle<'a>(&'a [u8], usize);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let size = self.1.min(self.0.len()).min(buf.len());
+            buf[..size].copy_from_slice(&self.0[..size]);
+            self.0 = &self.0[size..];
+            Ok(size)
+        }
+    }
+
+    fn minify_to(input: &str, step: usize, level: Level) -> Result<String, StreamError> {
+        let mut output = vec![];
+        Minifier::default().minify_to(Trickle(input.as_bytes(), step), &mut output, level)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_minify_to() {
+        let input = r#"
+            @charset "utf-8";
+            a { color: red; content: "}" } /* } */
+            @media print { a { color: black } .ы { margin: 0px } }
+            b { background: url(a\}.png) }
+            c { background: url(a}b.png) url( "c)}d" ) URL(e{f) }
+            a { padding: 0 }
+        "#;
+        for level in [Level::Zero, Level::One, Level::Two, Level::Three] {
+            let expected = Minifier::default().minify(input, level).unwrap();
+            for step in [1, 2, 3, 7, 1024] {
+                assert_eq!(minify_to(input, step, level).unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_minify_to_error() {
+        let input = "a { color: red }\nb { color: red } c {\n  color: red }}\nd { }";
+        let expected = Minifier::default().minify(input, Level::One).unwrap_err();
+        for step in [1, 5, 1024] {
+            match minify_to(input, step, Level::One) {
+                Err(StreamError::Parse(error)) => assert_eq!(error, expected),
+                result => panic!("unexpected {:?}", result),
+            }
+        }
+    }
+}
@@ -1,18 +1,39 @@
-use crate::structure::{CssEntities, CssEntity, Media, Name, Parameters, Supports, Value};
-use indexmap::map::IndexMap;
+use crate::structure::{
+    Block, Container, CssEntities, CssEntity, Layer, Media, Name, Parameter, Parameters, Selectors,
+    Supports, Value,
+};
 
+/// Transform of declarations with the registered callbacks, they are applied in the order of registration
 #[derive(Default)]
 pub struct Transformer {
     parameters: Vec<TransformerParameterFn>,
 }
 
 pub enum TransformerParameterFn {
-    Name(Box<dyn Fn(Name) -> Name + Send + Sync>),
-    Value(Box<dyn Fn(Value) -> Value + Send + Sync>),
+    Name(Box<dyn for<'a> Fn(Name<'a>) -> Name<'a> + Send + Sync>),
+    Value(Box<dyn for<'a> Fn(Value<'a>) -> Value<'a> + Send + Sync>),
+    /// Gets the declaration with the rule holding it and returns the declarations replacing it:
+    /// none to drop it or several to split it
+    Declaration(Box<DeclarationFn>),
 }
 
+pub type DeclarationFn =
+    dyn for<'a> Fn(Parameter<'a>, &DeclarationContext) -> Vec<Parameter<'a>> + Send + Sync;
+
 unsafe impl Send for TransformerParameterFn {}
 
+/// Rule which holds the declarations
+#[derive(Clone, Copy, Debug)]
+pub enum DeclarationContext<'a> {
+    /// Style rule, selectors are empty for declarations placed directly into a nested at-rule
+    Style(&'a Selectors<'a>),
+    /// Block of `@keyframes` with its selector: `from`, `50%`
+    Keyframe(&'a str),
+    FontFace,
+    Page,
+    Viewport,
+}
+
 impl Transformer {
     pub fn register_parameter(&mut self, transformer: TransformerParameterFn) {
         self.parameters.push(transformer)
@@ -20,76 +41,183 @@ impl Transformer {
 }
 
 impl Transform for Transformer {
-    fn transform_parameters(&self, parameters: Parameters) -> Parameters {
-        parameters
-            .0
-            .into_iter()
-            .map(|(mut name, mut value)| {
-                for transformer in self.parameters.iter() {
-                    match transformer {
-                        TransformerParameterFn::Name(t) => name = t(name),
-                        TransformerParameterFn::Value(t) => value = t(value),
-                    }
-                }
-                (name, value)
+    fn transform_parameters<'a>(&self, parameters: Parameters<'a>) -> Parameters<'a> {
+        self.transform_declarations(
+            parameters,
+            &DeclarationContext::Style(&Selectors::default()),
+        )
+    }
+
+    fn transform_declarations<'a>(
+        &self,
+        parameters: Parameters<'a>,
+        context: &DeclarationContext,
+    ) -> Parameters<'a> {
+        self.parameters
+            .iter()
+            .fold(parameters.0, |parameters, transformer| {
+                parameters
+                    .into_iter()
+                    .flat_map(|mut parameter| match transformer {
+                        TransformerParameterFn::Name(t) => {
+                            parameter.name = t(parameter.name);
+                            vec![parameter]
+                        }
+                        TransformerParameterFn::Value(t) => {
+                            parameter.value = t(parameter.value);
+                            vec![parameter]
+                        }
+                        TransformerParameterFn::Declaration(t) => t(parameter, context),
+                    })
+                    .collect()
             })
-            .collect::<IndexMap<Name, Value>>()
             .into()
     }
 }
 
+/// Rewrite of the css tree in the visitor style: every hook gets the node and returns
+/// its replacement. By default nodes are kept and their children are visited
+///
+/// ```rust
+/// use css_minify::optimizations::{Level, MinifierBuilder, Transform};
+/// use css_minify::structure::{CssEntity, Parameter};
+///
+/// struct Cleanup;
+///
+/// impl Transform for Cleanup {
+///     fn transform_entity<'a>(&self, entity: CssEntity<'a>) -> Option<CssEntity<'a>> {
+///         match &entity {
+///             CssEntity::Block(block) if block.selectors.to_string().contains(".debug") => None,
+///             _ => Some(self.transform(entity)),
+///         }
+///     }
+///
+///     fn transform_parameter<'a>(&self, mut parameter: Parameter<'a>) -> Option<Parameter<'a>> {
+///         parameter.name = parameter.name.replace("--old-", "--new-").into();
+///         parameter.value = parameter.value.replace("var(--old-", "var(--new-").into();
+///         Some(parameter)
+///     }
+/// }
+///
+/// let minifier = MinifierBuilder::default().push(Level::Zero, Cleanup).build();
+/// assert_eq!(
+///     minifier.minify(".debug { outline: red } a { --old-x: 1px; margin: var(--old-x) }", Level::One),
+///     Ok("a{--new-x:1px;margin:var(--new-x)}".into())
+/// );
+/// ```
 pub trait Transform {
-    fn transform_parameters(&self, parameters: Parameters) -> Parameters;
-    fn transform(&self, entity: CssEntity) -> CssEntity {
+    /// Declarations of a rule, by default every one is visited with [`Transform::transform_parameter`]
+    fn transform_parameters<'a>(&self, parameters: Parameters<'a>) -> Parameters<'a> {
+        parameters
+            .0
+            .into_iter()
+            .filter_map(|parameter| self.transform_parameter(parameter))
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// Declarations with the rule holding them, by default [`Transform::transform_parameters`]
+    fn transform_declarations<'a>(
+        &self,
+        parameters: Parameters<'a>,
+        _context: &DeclarationContext,
+    ) -> Parameters<'a> {
+        self.transform_parameters(parameters)
+    }
+
+    /// Single declaration, `None` removes it
+    fn transform_parameter<'a>(&self, parameter: Parameter<'a>) -> Option<Parameter<'a>> {
+        Some(parameter)
+    }
+
+    fn transform_selectors<'a>(&self, selectors: Selectors<'a>) -> Selectors<'a> {
+        selectors
+    }
+
+    /// Prelude of the at-rule: media queries of `@media`, conditions of `@container`
+    /// and `@supports`, selectors of `@page` and the name of `@keyframes`
+    fn transform_prelude<'a>(&self, _at_rule: &str, prelude: Name<'a>) -> Name<'a> {
+        prelude
+    }
+
+    /// Css rule with its selectors, declarations and nested rules
+    fn transform_block<'a>(&self, mut block: Block<'a>) -> Block<'a> {
+        block.selectors = self.transform_selectors(block.selectors);
+        block.parameters = self.transform_declarations(
+            block.parameters,
+            &DeclarationContext::Style(&block.selectors),
+        );
+        block.entities = self.transform_many(block.entities);
+        block
+    }
+
+    /// Any rule, `None` removes it. By default it is visited with [`Transform::transform`]
+    fn transform_entity<'a>(&self, entity: CssEntity<'a>) -> Option<CssEntity<'a>> {
+        Some(self.transform(entity))
+    }
+
+    /// Visits the prelude and the content of the rule
+    fn transform<'a>(&self, entity: CssEntity<'a>) -> CssEntity<'a> {
         match entity {
-            CssEntity::Block(mut block) => {
-                block.parameters = self.transform_parameters(block.parameters);
-                CssEntity::Block(block)
-            }
+            CssEntity::Block(block) => CssEntity::Block(self.transform_block(block)),
             CssEntity::Media(Media { screen, entities }) => CssEntity::Media(Media {
-                screen,
-                entities: entities
-                    .0
-                    .into_iter()
-                    .map(|block| self.transform(block))
-                    .collect::<Vec<_>>()
-                    .into(),
+                screen: self.transform_prelude("media", screen),
+                entities: self.transform_many(entities),
+            }),
+            CssEntity::Container(Container {
+                name,
+                condition,
+                entities,
+            }) => CssEntity::Container(Container {
+                name,
+                condition: self.transform_prelude("container", condition),
+                entities: self.transform_many(entities),
             }),
             CssEntity::Supports(Supports {
                 conditions,
                 entities,
             }) => CssEntity::Supports(Supports {
-                conditions,
-                entities: entities
-                    .0
-                    .into_iter()
-                    .map(|block| self.transform(block))
-                    .collect::<Vec<_>>()
-                    .into(),
+                conditions: self.transform_prelude("supports", conditions),
+                entities: self.transform_many(entities),
+            }),
+            CssEntity::Layer(Layer { names, entities }) => CssEntity::Layer(Layer {
+                names,
+                entities: entities.map(|entities| self.transform_many(entities)),
             }),
             CssEntity::FontFace(mut font_face) => {
-                font_face.parameters = self.transform_parameters(font_face.parameters);
+                font_face.parameters = self
+                    .transform_declarations(font_face.parameters, &DeclarationContext::FontFace);
                 CssEntity::FontFace(font_face)
             }
             CssEntity::Page(mut page) => {
-                page.parameters = self.transform_parameters(page.parameters);
+                page.selectors = page
+                    .selectors
+                    .map(|selectors| self.transform_prelude("page", selectors));
+                page.parameters =
+                    self.transform_declarations(page.parameters, &DeclarationContext::Page);
                 CssEntity::Page(page)
             }
             CssEntity::Viewport(mut viewport) => {
-                viewport.parameters = self.transform_parameters(viewport.parameters);
+                viewport.parameters =
+                    self.transform_declarations(viewport.parameters, &DeclarationContext::Viewport);
                 CssEntity::Viewport(viewport)
             }
             CssEntity::MsViewport(mut viewport) => {
-                viewport.parameters = self.transform_parameters(viewport.parameters);
+                viewport.parameters =
+                    self.transform_declarations(viewport.parameters, &DeclarationContext::Viewport);
                 CssEntity::MsViewport(viewport)
             }
             CssEntity::Keyframes(mut kf) => {
+                kf.name = self.transform_prelude("keyframes", kf.name);
                 kf.blocks.0 = kf
                     .blocks
                     .0
                     .into_iter()
                     .map(|mut block| {
-                        block.parameters = self.transform_parameters(block.parameters);
+                        block.parameters = self.transform_declarations(
+                            block.parameters,
+                            &DeclarationContext::Keyframe(&block.name),
+                        );
                         block
                     })
                     .collect();
@@ -98,7 +226,82 @@ pub trait Transform {
             CssEntity::At(at) => CssEntity::At(at),
         }
     }
-    fn transform_many(&self, blocks: CssEntities) -> CssEntities {
-        CssEntities(blocks.0.into_iter().map(|b| self.transform(b)).collect())
+
+    /// Entities of the stylesheet or of a nested rule
+    fn transform_many<'a>(&self, entities: CssEntities<'a>) -> CssEntities<'a> {
+        CssEntities(
+            entities
+                .0
+                .into_iter()
+                .filter_map(|entity| self.transform_entity(entity))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::optimizations::transformer::{
+        DeclarationContext, Transform, Transformer, TransformerParameterFn,
+    };
+    use crate::parse;
+    use crate::structure::{Block, Name, Parameter};
+
+    struct Rename;
+
+    impl Transform for Rename {
+        fn transform_prelude<'a>(&self, at_rule: &str, prelude: Name<'a>) -> Name<'a> {
+            match at_rule {
+                "keyframes" | "media" => prelude.replace("old", "new").into(),
+                _ => prelude,
+            }
+        }
+
+        fn transform_block<'a>(&self, mut block: Block<'a>) -> Block<'a> {
+            block.parameters.remove("outline");
+            block.entities = self.transform_many(block.entities);
+            block
+        }
+    }
+
+    #[test]
+    fn test_visitor_hooks() {
+        let stylesheet = parse(
+            "@media (old) { a { outline: red; b { outline: 0 } } } @keyframes old { to { outline: 0 } }",
+        )
+        .unwrap();
+        assert_eq!(
+            Rename.transform_many(stylesheet).to_string(),
+            "@media (new){a{b{}}}@keyframes new{to{outline:0}}"
+        );
+    }
+
+    #[test]
+    fn test_declaration_transformer() {
+        let mut transformer = Transformer::default();
+        transformer.register_parameter(TransformerParameterFn::Declaration(Box::new(
+            |parameter, context| match (parameter.name.as_ref(), context) {
+                ("margin-inline", _) => ["margin-left", "margin-right"]
+                    .into_iter()
+                    .map(|name| Parameter::from((name.into(), parameter.value.clone())))
+                    .collect(),
+                ("color", DeclarationContext::Keyframe(_)) => vec![],
+                ("color", DeclarationContext::Style(selectors)) if selectors.to_string() == "a" => {
+                    vec![Parameter::from(("color".into(), "blue".into()))]
+                }
+                _ => vec![parameter],
+            },
+        )));
+        transformer.register_parameter(TransformerParameterFn::Value(Box::new(|value| {
+            value.to_uppercase().into()
+        })));
+        let stylesheet = parse(
+            "a { color: red; margin-inline: auto } b { color: red } @keyframes k { to { color: red; top: 0 } }",
+        )
+        .unwrap();
+        assert_eq!(
+            transformer.transform_many(stylesheet).to_string(),
+            "a{color:BLUE;margin-left:AUTO;margin-right:AUTO}b{color:RED}@keyframes k{to{top:0}}"
+        );
     }
 }
@@ -0,0 +1,200 @@
+use crate::optimizations::color::{is_color_function, is_color_property, minify_color};
+use crate::optimizations::numbers::{is_length, minify_dimension, minify_number, Decimal};
+use crate::structure::{ComponentValue, ComponentValues, Name, Value};
+
+/// Functions where zero lengths keep their units, unitless zero is a number there
+const MATH_FUNCTIONS: [&str; 6] = ["calc", "min", "max", "clamp", "var", "env"];
+
+/// Properties where unitless zero is not a length
+const ZERO_UNIT_PROPERTIES: [&str; 2] = ["flex", "flex-basis"];
+
+/// Removes whitespaces around commas and before `!important`, shortens numbers
+/// and drops units of zero lengths. Strings and urls are never changed
+pub(crate) fn minify_value<'a>(name: &Name, value: Value<'a>) -> Value<'a> {
+    if name.starts_with("--") {
+        let minified = minify_colors(ComponentValues::parse(&value)).to_string();
+        return unchanged_or(value, minified);
+    }
+    let name = unprefixed(name);
+    if name == "unicode-range" {
+        return value;
+    }
+    let keep_units = ZERO_UNIT_PROPERTIES.contains(&name.as_str());
+    let minified = minify_values(
+        ComponentValues::parse(&value),
+        keep_units,
+        is_color_property(&name),
+    )
+    .to_string();
+    unchanged_or(value, minified)
+}
+
+/// The borrowed value is kept when the minification didn't change it
+fn unchanged_or<'a>(value: Value<'a>, minified: String) -> Value<'a> {
+    match *value == minified {
+        true => value,
+        false => minified.into(),
+    }
+}
+
+/// Custom properties may be used in `calc()` where units matter and their keywords
+/// may be anything, so only hex colors and color functions are shortened there
+fn minify_colors(values: ComponentValues) -> ComponentValues {
+    values
+        .0
+        .into_iter()
+        .map(|value| match minify_color(&value, false) {
+            Some(color) => color,
+            None => match value {
+                ComponentValue::Function(name, arguments) => {
+                    ComponentValue::Function(name, minify_colors(arguments))
+                }
+                ComponentValue::Block(open, content) => {
+                    ComponentValue::Block(open, minify_colors(content))
+                }
+                value => value,
+            },
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// `keep_units` is set in math functions and properties where zero lengths keep units,
+/// `colors` is set in color properties and functions, elsewhere colors are kept as written
+fn minify_values(values: ComponentValues, keep_units: bool, colors: bool) -> ComponentValues {
+    let values = values.0;
+    let mut minified = Vec::with_capacity(values.len());
+    for (i, value) in values.iter().enumerate() {
+        if let Some(color) = minify_color(value, true).filter(|_| colors) {
+            minified.push(color);
+            continue;
+        }
+        let value = match value.clone() {
+            ComponentValue::Whitespace => {
+                let previous = minified.last();
+                let next = values.get(i + 1);
+                if previous
+                    .is_none_or(|p| matches!(p, ComponentValue::Comma | ComponentValue::Whitespace))
+                    || next.is_none_or(|n| {
+                        matches!(n, ComponentValue::Comma | ComponentValue::Delim('!'))
+                    })
+                {
+                    continue;
+                }
+                ComponentValue::Whitespace
+            }
+            ComponentValue::Number(number) => ComponentValue::Number(minify_number(&number)),
+            ComponentValue::Percentage(number) => {
+                ComponentValue::Percentage(minify_number(&number))
+            }
+            ComponentValue::Dimension(number, unit)
+                if !keep_units
+                    && is_length(&unit)
+                    && Decimal::parse(&number).is_some_and(|n| n.is_zero()) =>
+            {
+                ComponentValue::Number("0".into())
+            }
+            ComponentValue::Dimension(number, unit) => {
+                let (number, unit) = minify_dimension(&number, &unit);
+                ComponentValue::Dimension(number, unit)
+            }
+            ComponentValue::Function(name, arguments) => {
+                let function = unprefixed(&name);
+                let keep_units = keep_units || MATH_FUNCTIONS.contains(&function.as_str());
+                let colors = colors || is_color_function(&function);
+                ComponentValue::Function(name, minify_values(arguments, keep_units, colors))
+            }
+            ComponentValue::Block(open, content) => {
+                ComponentValue::Block(open, minify_values(content, keep_units, colors))
+            }
+            value => value,
+        };
+        minified.push(value);
+    }
+    ComponentValues(minified)
+}
+
+/// Lowercase name without the vendor prefix: `-webkit-calc` to `calc`
+fn unprefixed(name: &str) -> String {
+    let name = name.to_ascii_lowercase();
+    match name.strip_prefix('-') {
+        Some(prefixed) => prefixed
+            .split_once('-')
+            .map_or(name.clone(), |(_, n)| n.into()),
+        None => name,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::optimizations::values::minify_value;
+
+    #[test]
+    fn test_minify_value() {
+        for (value, expected) in [
+            ("0px 0.5em 0rem 0.0vh", "0 .5em 0 0"),
+            ("rgba(0, 0, 0, 0.5)", "rgba(0,0,0,.5)"),
+            ("-0.5px , 10px", "-.5px,10px"),
+            ("red !important", "red!important"),
+            (
+                "calc(0px + 10%) -webkit-calc(0px + 1px)",
+                "calc(0px + 10%) -webkit-calc(0px + 1px)",
+            ),
+            ("var(--x, 0px)", "var(--x,0px)"),
+            ("\"Foo 0.5, bar\", serif", "\"Foo 0.5, bar\",serif"),
+            ("url(a, 0.5.png)", "url(a, 0.5.png)"),
+            ("10.0px 0.0 50.0% 0%", "10px 0 50% 0%"),
+            ("0s 500ms 0deg", "0s .5s 0deg"),
+            ("-1.50e3px 10mm", "-1500px 1cm"),
+        ] {
+            assert_eq!(minify_value(&"margin".into(), value.into()), expected);
+        }
+    }
+
+    #[test]
+    fn test_minify_value_colors() {
+        for (name, value, expected) in [
+            ("border", "1px solid rgb(255, 0, 0)", "1px solid red"),
+            (
+                "box-shadow",
+                "0 0 2px #FFFFFF, inset 0 0 1px black",
+                "0 0 2px #fff,inset 0 0 1px #000",
+            ),
+            ("color", "white !important", "#fff!important"),
+            (
+                "background-image",
+                "linear-gradient(to right, white 0%, rgba(0, 0, 0, 0.5))",
+                "linear-gradient(to right,#fff 0%,rgba(0,0,0,.5))",
+            ),
+            ("background", "#FFFFFF url(a.png)", "#fff url(a.png)"),
+            ("animation", "white 1s", "white 1s"),
+            ("grid-area", "#FFFFFF", "#FFFFFF"),
+            (
+                "filter",
+                "drop-shadow(0 0 2px #FFFFFF)",
+                "drop-shadow(0 0 2px #fff)",
+            ),
+            ("color", "rgba(0, 0, 0, 0)", "transparent"),
+            ("--brand", "  #FF0000 white 0px", " red white 0px"),
+            ("--shadow", "var(--x, rgb(0 0 0))", "var(--x, #000)"),
+        ] {
+            assert_eq!(minify_value(&name.into(), value.into()), expected);
+        }
+    }
+
+    #[test]
+    fn test_minify_value_property() {
+        for (name, value, expected) in [
+            ("flex", "1 1 0px", "1 1 0px"),
+            ("-webkit-flex-basis", "0.0px", "0px"),
+            ("unicode-range", "U+0025-00FF", "U+0025-00FF"),
+            (
+                "transition",
+                "opacity 0.50s , color 100ms",
+                "opacity .5s,color .1s",
+            ),
+        ] {
+            assert_eq!(minify_value(&name.into(), value.into()), expected);
+        }
+    }
+}
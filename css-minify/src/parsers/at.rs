@@ -5,27 +5,65 @@ use crate::parsers::utils::{
     some_block_with_prefix, some_block_with_prefix_and_value, space,
 };
 use crate::structure::{
-    At, CharsetAt, FontFace, ImportAt, KeyframeBlock, KeyframeBlocks, Keyframes, Media, MsViewport,
-    NamespaceAt, Page, Supports, Value, Viewport,
+    At, CharsetAt, Container, CssEntities, FontFace, ImportAt, KeyframeBlock, KeyframeBlocks,
+    Keyframes, Layer, Media, MsViewport, Name, NamespaceAt, Page, Supports, Value, Viewport,
 };
 use nom::branch::alt;
 use nom::bytes::complete::{is_not, tag};
 use nom::character::complete::char;
 use nom::combinator::{into, map, map_parser, opt, rest};
 use nom::error::Error as IError;
-use nom::multi::many0;
+use nom::multi::{many0, separated_list0};
 use nom::sequence::{preceded, terminated, tuple};
-use nom::IResult;
+use nom::{IResult, Parser};
 
-pub fn parse_media(input: &str) -> IResult<&str, Media> {
+pub fn parse_media(input: &str) -> IResult<&str, Media<'_>> {
+    media(parse_entities)(input)
+}
+
+pub(crate) fn media<'a, P: Parser<&'a str, CssEntities<'a>, IError<&'a str>>>(
+    entities: P,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Media<'a>> {
     into(some_block_with_prefix_and_value(
         "@media",
         parse_to_block_open,
-        parse_entities,
-    ))(input)
+        entities,
+    ))
+}
+
+pub fn parse_container(input: &str) -> IResult<&str, Container<'_>> {
+    container(parse_entities)(input)
+}
+
+pub(crate) fn container<'a, P: Parser<&'a str, CssEntities<'a>, IError<&'a str>>>(
+    entities: P,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Container<'a>> {
+    map(
+        some_block_with_prefix_and_value("@container", parse_to_block_open, entities),
+        |(prelude, entities): (&str, _)| {
+            let (name, condition) = split_container_prelude(prelude);
+            Container {
+                name: name.map(Name::from),
+                condition: condition.into(),
+                entities,
+            }
+        },
+    )
+}
+
+/// Container name is an optional identifier before the container query
+fn split_container_prelude(prelude: &str) -> (Option<&str>, &str) {
+    let (first, rest) = prelude
+        .split_once(char::is_whitespace)
+        .unwrap_or((prelude, ""));
+    if first.contains('(') || matches!(first, "not" | "and" | "or" | "") {
+        (None, prelude)
+    } else {
+        (Some(first), rest.trim())
+    }
 }
 
-pub fn parse_page(input: &str) -> IResult<&str, Page> {
+pub fn parse_page(input: &str) -> IResult<&str, Page<'_>> {
     into(some_block_with_prefix_and_value(
         "@page",
         opt(parse_to_block_open),
@@ -33,15 +71,51 @@ pub fn parse_page(input: &str) -> IResult<&str, Page> {
     ))(input)
 }
 
-pub fn parse_supports(input: &str) -> IResult<&str, Supports> {
+pub fn parse_supports(input: &str) -> IResult<&str, Supports<'_>> {
+    supports(parse_entities)(input)
+}
+
+pub(crate) fn supports<'a, P: Parser<&'a str, CssEntities<'a>, IError<&'a str>>>(
+    entities: P,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Supports<'a>> {
     into(some_block_with_prefix_and_value(
         "@supports",
         parse_to_block_open,
-        parse_entities,
-    ))(input)
+        entities,
+    ))
+}
+
+pub fn parse_layer(input: &str) -> IResult<&str, Layer<'_>> {
+    layer(parse_entities)(input)
+}
+
+pub(crate) fn layer<'a, P: Parser<&'a str, CssEntities<'a>, IError<&'a str>>>(
+    entities: P,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Layer<'a>> {
+    preceded(
+        non_useless(tag("@layer")),
+        alt((
+            map(terminated(parse_layer_names, char(';')), |names| {
+                (names, None).into()
+            }),
+            map(
+                tuple((parse_layer_names, some_block(entities))),
+                |(names, entities)| (names, Some(entities)).into(),
+            ),
+        )),
+    )
+}
+
+fn parse_layer_names(input: &str) -> IResult<&str, Vec<Name<'_>>> {
+    separated_list0(
+        char(','),
+        map(non_useless(is_not(",;{}")), |name: &str| {
+            Name::from(name.trim())
+        }),
+    )(input)
 }
 
-pub fn parse_keyframes(input: &str) -> IResult<&str, Keyframes> {
+pub fn parse_keyframes(input: &str) -> IResult<&str, Keyframes<'_>> {
     into(non_useless(tuple((
         alt((
             map(tag("@keyframes"), |_| false),
@@ -52,30 +126,30 @@ pub fn parse_keyframes(input: &str) -> IResult<&str, Keyframes> {
     ))))(input)
 }
 
-pub fn parse_keyframe_blocks(input: &str) -> IResult<&str, KeyframeBlocks> {
+pub fn parse_keyframe_blocks(input: &str) -> IResult<&str, KeyframeBlocks<'_>> {
     into(many0(non_useless(parse_keyframe_block)))(input)
 }
 
-pub fn parse_keyframe_block(input: &str) -> IResult<&str, KeyframeBlock> {
+pub fn parse_keyframe_block(input: &str) -> IResult<&str, KeyframeBlock<'_>> {
     into(tuple((
         non_useless(is_not_block_ending(parse_to_block_open)),
         some_block(parse_parameters),
     )))(input)
 }
 
-pub fn parse_font_face(input: &str) -> IResult<&str, FontFace> {
+pub fn parse_font_face(input: &str) -> IResult<&str, FontFace<'_>> {
     into(some_block_with_prefix("@font-face", parse_parameters))(input)
 }
 
-pub fn parse_viewport(input: &str) -> IResult<&str, Viewport> {
+pub fn parse_viewport(input: &str) -> IResult<&str, Viewport<'_>> {
     into(some_block_with_prefix("@viewport", parse_parameters))(input)
 }
 
-pub fn parse_ms_viewport(input: &str) -> IResult<&str, MsViewport> {
+pub fn parse_ms_viewport(input: &str) -> IResult<&str, MsViewport<'_>> {
     into(some_block_with_prefix("@-ms-viewport", parse_parameters))(input)
 }
 
-pub fn parse_at(input: &str) -> IResult<&str, At> {
+pub fn parse_at(input: &str) -> IResult<&str, At<'_>> {
     non_useless(alt((
         into(parse_charset),
         into(parse_namespace),
@@ -83,11 +157,11 @@ pub fn parse_at(input: &str) -> IResult<&str, At> {
     )))(input)
 }
 
-pub fn parse_charset(input: &str) -> IResult<&str, CharsetAt> {
+pub fn parse_charset(input: &str) -> IResult<&str, CharsetAt<'_>> {
     map(simple_at("@charset"), |s: &str| Value::from(s).into())(input)
 }
 
-pub fn parse_namespace(input: &str) -> IResult<&str, NamespaceAt> {
+pub fn parse_namespace(input: &str) -> IResult<&str, NamespaceAt<'_>> {
     map(
         map_parser(
             simple_at("@namespace"),
@@ -97,7 +171,7 @@ pub fn parse_namespace(input: &str) -> IResult<&str, NamespaceAt> {
     )(input)
 }
 
-pub fn parse_import(input: &str) -> IResult<&str, ImportAt> {
+pub fn parse_import(input: &str) -> IResult<&str, ImportAt<'_>> {
     map(
         map_parser(
             simple_at("@import"),
@@ -116,12 +190,13 @@ fn simple_at<'a>(
 #[cfg(test)]
 mod test {
     use crate::parsers::at::{
-        parse_charset, parse_font_face, parse_import, parse_keyframes, parse_media,
-        parse_ms_viewport, parse_namespace, parse_page, parse_supports, parse_viewport,
+        parse_charset, parse_container, parse_font_face, parse_import, parse_keyframes,
+        parse_layer, parse_media, parse_ms_viewport, parse_namespace, parse_page, parse_supports,
+        parse_viewport,
     };
     use crate::structure::{
-        Block, CssEntity, FontFace, KeyframeBlock, Keyframes, Media, MsViewport, Name, Page,
-        Selector, SelectorWithPseudoClasses, Supports, Value, Viewport,
+        Block, Container, CssEntity, FontFace, KeyframeBlock, Keyframes, Layer, Media, MsViewport,
+        Name, Page, Selector, Supports, Value, Viewport,
     };
     use indexmap::map::IndexMap;
 
@@ -140,16 +215,45 @@ mod test {
                 Media {
                     screen: Value::from("only screen and (max-width: 992px)"),
                     entities: vec![CssEntity::Block(Block {
-                        selectors: vec![SelectorWithPseudoClasses(
-                            Some(Selector::Class("test".into())),
-                            vec![]
-                        )]
-                        .into(),
+                        selectors: vec![Selector::Class("test".into()).into()].into(),
                         parameters: {
                             let mut tmp = IndexMap::new();
                             tmp.insert("min-height".into(), "68px".into());
                             tmp.into()
-                        }
+                        },
+                        entities: Default::default(),
+                        location: Default::default(),
+                    })]
+                    .into()
+                }
+            ))
+        )
+    }
+
+    #[test]
+    fn test_container() {
+        assert_eq!(
+            parse_container(
+                r#"
+            @container card (min-width: 400px) {
+              .test {
+                min-height: 68px; }
+            }"#
+            ),
+            Ok((
+                "",
+                Container {
+                    name: Some(Name::from("card")),
+                    condition: Value::from("(min-width: 400px)"),
+                    entities: vec![CssEntity::Block(Block {
+                        selectors: vec![Selector::Class("test".into()).into()].into(),
+                        parameters: {
+                            let mut tmp = IndexMap::new();
+                            tmp.insert("min-height".into(), "68px".into());
+                            tmp.into()
+                        },
+                        entities: Default::default(),
+                        location: Default::default(),
                     })]
                     .into()
                 }
@@ -157,6 +261,21 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_container_without_name() {
+        assert_eq!(
+            parse_container("@container not (width < 400px) and style(--responsive: true) {}"),
+            Ok((
+                "",
+                Container {
+                    name: None,
+                    condition: Value::from("not (width < 400px) and style(--responsive: true)"),
+                    entities: vec![].into()
+                }
+            ))
+        )
+    }
+
     #[test]
     fn test_page() {
         assert_eq!(
@@ -171,7 +290,7 @@ mod test {
                     selectors: Some(Name::from("test")),
                     parameters: {
                         let mut tmp: IndexMap<Name, Value> = IndexMap::new();
-                        tmp.insert("size".to_string(), "a3".to_string());
+                        tmp.insert("size".into(), "a3".into());
                         tmp
                     }
                     .into()
@@ -194,7 +313,7 @@ mod test {
                     selectors: None,
                     parameters: {
                         let mut tmp: IndexMap<Name, Value> = IndexMap::new();
-                        tmp.insert("size".to_string(), "a3".to_string());
+                        tmp.insert("size".into(), "a3".into());
                         tmp
                     }
                     .into()
@@ -218,16 +337,14 @@ mod test {
                 Supports {
                     conditions: Value::from("(-ms-ime-align: auto)"),
                     entities: vec![CssEntity::Block(Block {
-                        selectors: vec![SelectorWithPseudoClasses(
-                            Some(Selector::Class("test".into())),
-                            vec![]
-                        )]
-                        .into(),
+                        selectors: vec![Selector::Class("test".into()).into()].into(),
                         parameters: {
                             let mut tmp = IndexMap::new();
                             tmp.insert("min-height".into(), "68px".into());
                             tmp.into()
-                        }
+                        },
+                        entities: Default::default(),
+                        location: Default::default(),
                     })]
                     .into()
                 }
@@ -235,6 +352,66 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_layer_statement() {
+        assert_eq!(
+            parse_layer("@layer base, components;"),
+            Ok((
+                "",
+                Layer {
+                    names: vec!["base".into(), "components".into()],
+                    entities: None,
+                }
+            ))
+        )
+    }
+
+    #[test]
+    fn test_layer() {
+        assert_eq!(
+            parse_layer(
+                r#"
+            @layer framework.base {
+              .test {
+                min-height: 68px; }
+            }"#
+            ),
+            Ok((
+                "",
+                Layer {
+                    names: vec!["framework.base".into()],
+                    entities: Some(
+                        vec![CssEntity::Block(Block {
+                            selectors: vec![Selector::Class("test".into()).into()].into(),
+                            parameters: {
+                                let mut tmp = IndexMap::new();
+                                tmp.insert("min-height".into(), "68px".into());
+                                tmp.into()
+                            },
+                            entities: Default::default(),
+                            location: Default::default(),
+                        })]
+                        .into()
+                    )
+                }
+            ))
+        )
+    }
+
+    #[test]
+    fn test_anonymous_layer() {
+        assert_eq!(
+            parse_layer("@layer { }"),
+            Ok((
+                "",
+                Layer {
+                    names: vec![],
+                    entities: Some(vec![].into())
+                }
+            ))
+        )
+    }
+
     #[test]
     fn test_keyframes() {
         assert_eq!(
@@ -299,11 +476,10 @@ mod test {
                 FontFace {
                     parameters: {
                         let mut tmp: IndexMap<Name, Value> = IndexMap::new();
-                        tmp.insert("font-family".to_string(), "\"Open Sans\"".to_string());
+                        tmp.insert("font-family".into(), "\"Open Sans\"".into());
                         tmp.insert(
-                            "src".to_string(),
-                            "url(/fonts/OpenSans-Regular-webfont.woff2) format(\"woff2\")"
-                                .to_string(),
+                            "src".into(),
+                            "url(/fonts/OpenSans-Regular-webfont.woff2) format(\"woff2\")".into(),
                         );
                         tmp
                     }
@@ -328,8 +504,8 @@ mod test {
                 Viewport {
                     parameters: {
                         let mut tmp: IndexMap<Name, Value> = IndexMap::new();
-                        tmp.insert("min-width".to_string(), "640px".to_string());
-                        tmp.insert("max-width".to_string(), "800px".to_string());
+                        tmp.insert("min-width".into(), "640px".into());
+                        tmp.insert("max-width".into(), "800px".into());
                         tmp
                     }
                     .into()
@@ -353,8 +529,8 @@ mod test {
                 MsViewport {
                     parameters: {
                         let mut tmp: IndexMap<Name, Value> = IndexMap::new();
-                        tmp.insert("min-width".to_string(), "640px".to_string());
-                        tmp.insert("max-width".to_string(), "800px".to_string());
+                        tmp.insert("min-width".into(), "640px".into());
+                        tmp.insert("max-width".into(), "800px".into());
                         tmp
                     }
                     .into()
@@ -1,23 +1,103 @@
-use crate::parsers::parameters::parse_parameters;
+use crate::parsers::at::{container, layer, media, supports};
+use crate::parsers::parameters::parse_located_parameter;
 use crate::parsers::selector::parse_selectors;
-use crate::parsers::utils::{non_useless, some_block};
-use crate::structure::Block;
-use nom::combinator::into;
+use crate::parsers::utils::{is_not_block_ending, located, non_useless, some_block};
+use crate::structure::{Block, CssEntities, CssEntity, Parameter, Parameters};
+use nom::branch::alt;
+use nom::combinator::{into, map};
+use nom::multi::many0;
 use nom::sequence::tuple;
 use nom::IResult;
 
-pub fn parse_block(input: &str) -> IResult<&str, Block> {
-    into(tuple((
-        non_useless(parse_selectors),
-        some_block(parse_parameters),
-    )))(input)
+enum BlockContent<'a> {
+    Parameter(Parameter<'a>),
+    Entity(CssEntity<'a>),
+}
+
+pub fn parse_block(input: &str) -> IResult<&str, Block<'_>> {
+    map(
+        tuple((
+            non_useless(located(parse_selectors)),
+            some_block(parse_block_content),
+        )),
+        |((selectors, location), (parameters, entities))| Block {
+            selectors,
+            parameters,
+            entities,
+            location,
+        },
+    )(input)
+}
+
+/// Declarations and nested rules of the block. Declarations after a nested rule
+/// are kept in the bare blocks between the rules, so their order isn't changed
+pub fn parse_block_content(input: &str) -> IResult<&str, (Parameters<'_>, CssEntities<'_>)> {
+    map(
+        many0(non_useless(alt((
+            map(
+                is_not_block_ending(parse_located_parameter),
+                BlockContent::Parameter,
+            ),
+            map(parse_nested_entity, BlockContent::Entity),
+        )))),
+        |content| {
+            let mut parameters = Parameters::default();
+            let mut entities = CssEntities::default();
+            content
+                .into_iter()
+                .for_each(|c| match (c, entities.last_mut()) {
+                    (BlockContent::Parameter(p), None) => parameters.push(p),
+                    (BlockContent::Parameter(p), Some(CssEntity::Block(block)))
+                        if block.is_bare() =>
+                    {
+                        block.parameters.push(p)
+                    }
+                    (BlockContent::Parameter(p), Some(_)) => {
+                        entities.push(bare_block(vec![p].into()))
+                    }
+                    (BlockContent::Entity(e), _) => entities.push(e),
+                });
+            (parameters, entities)
+        },
+    )(input)
+}
+
+fn bare_block(parameters: Parameters) -> CssEntity {
+    Block {
+        selectors: Default::default(),
+        parameters,
+        entities: Default::default(),
+        location: Default::default(),
+    }
+    .into()
+}
+
+fn parse_nested_entity(input: &str) -> IResult<&str, CssEntity<'_>> {
+    alt((
+        into(media(parse_nested_entities)),
+        into(container(parse_nested_entities)),
+        into(supports(parse_nested_entities)),
+        into(layer(parse_nested_entities)),
+        into(parse_block),
+    ))(input)
+}
+
+/// Content of the at-rule nested into a block,
+/// declarations of the enclosing rule are kept in the bare block
+fn parse_nested_entities(input: &str) -> IResult<&str, CssEntities<'_>> {
+    map(parse_block_content, |(parameters, mut entities)| {
+        if !parameters.is_empty() {
+            entities.insert(0, bare_block(parameters));
+        }
+        entities
+    })(input)
 }
 
 #[cfg(test)]
 mod test {
     use crate::parsers::block::parse_block;
     use crate::parsers::utils::non_useless;
-    use crate::structure::{Block, Selector, SelectorWithPseudoClasses};
+    use crate::structure::{Block, Selector};
     use indexmap::map::IndexMap;
 
     #[test]
@@ -36,8 +116,8 @@ mod test {
                 "",
                 Block {
                     selectors: vec![
-                        SelectorWithPseudoClasses(Some(Selector::Id("some_id".into())), vec![]),
-                        SelectorWithPseudoClasses(Some(Selector::Tag("input".into())), vec![]),
+                        Selector::Id("some_id".into()).into(),
+                        Selector::Tag("input".into()).into(),
                     ]
                     .into(),
                     parameters: {
@@ -45,7 +125,9 @@ mod test {
                         tmp.insert("padding".into(), "5px 3px".into());
                         tmp.insert("color".into(), "white".into());
                         tmp.into()
-                    }
+                    },
+                    entities: Default::default(),
+                    location: Default::default(),
                 }
             ))
         )
@@ -66,8 +148,8 @@ mod test {
                 "",
                 Block {
                     selectors: vec![
-                        SelectorWithPseudoClasses(Some(Selector::Id("some_id".into())), vec![]),
-                        SelectorWithPseudoClasses(Some(Selector::Tag("input".into())), vec![]),
+                        Selector::Id("some_id".into()).into(),
+                        Selector::Tag("input".into()).into(),
                     ]
                     .into(),
                     parameters: {
@@ -75,9 +157,46 @@ mod test {
                         tmp.insert("padding".into(), "5px 3px".into());
                         tmp.insert("color".into(), "white".into());
                         tmp.into()
-                    }
+                    },
+                    entities: Default::default(),
+                    location: Default::default(),
                 }
             ))
         )
     }
+
+    #[test]
+    fn test_block_nested() {
+        let (rest, block) = non_useless(parse_block)(
+            r#"
+            .card {
+                color: red;
+                &:hover { color: blue }
+                .title { font-weight: bold; }
+                @media print { padding: 0; .title { color: black } }
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(block.parameters.len(), 1);
+        assert_eq!(block.entities.len(), 3);
+        assert_eq!(
+            block.to_string(),
+            ".card{color:red;&:hover{color:blue}.title{font-weight:bold}@media print{padding:0;.title{color:black}}}"
+        )
+    }
+
+    #[test]
+    fn test_block_interleaved() {
+        let (_, block) =
+            parse_block(".a{color:red;&{color:blue}color:green;margin:0;@media print{b{c:d}e:f}}")
+                .unwrap();
+        assert_eq!(block.parameters.len(), 1);
+        assert_eq!(block.entities.len(), 3);
+        assert_eq!(
+            block.to_string(),
+            ".a{color:red;&{color:blue}color:green;margin:0;@media print{b{c:d}e:f}}"
+        )
+    }
 }
@@ -1,33 +1,60 @@
 use crate::parsers::at::{
-    parse_at, parse_font_face, parse_keyframes, parse_media, parse_ms_viewport, parse_page,
-    parse_supports, parse_viewport,
+    parse_at, parse_container, parse_font_face, parse_keyframes, parse_layer, parse_media,
+    parse_ms_viewport, parse_page, parse_supports, parse_viewport,
 };
 use crate::parsers::block::parse_block;
 use crate::parsers::utils::non_useless;
 use crate::structure::{CssEntities, CssEntity};
-use nom::branch::alt;
 use nom::combinator::{all_consuming, into, map};
+use nom::error::{Error, ErrorKind};
 use nom::multi::many0;
-use nom::IResult;
+use nom::{Err, IResult};
 
-pub fn parse_css(input: &str) -> IResult<&str, CssEntities> {
+pub fn parse_css(input: &str) -> IResult<&str, CssEntities<'_>> {
     all_consuming(parse_entities)(input)
 }
 
-pub fn parse_entities(input: &str) -> IResult<&str, CssEntities> {
+pub fn parse_entities(input: &str) -> IResult<&str, CssEntities<'_>> {
     map(non_useless(many0(parse_entity)), |css| css.into())(input)
 }
 
-pub fn parse_entity(input: &str) -> IResult<&str, CssEntity> {
-    alt((
-        into(parse_media),
-        into(parse_at),
-        into(parse_keyframes),
-        into(parse_font_face),
-        into(parse_viewport),
-        into(parse_ms_viewport),
-        into(parse_supports),
-        into(parse_page),
-        into(parse_block),
-    ))(input)
+type EntityParser = fn(&str) -> IResult<&str, CssEntity>;
+
+/// Parsers of the top level entities, they are tried in order
+const ENTITY_PARSERS: [EntityParser; 11] = [
+    |i| into(parse_media)(i),
+    |i| into(parse_container)(i),
+    |i| into(parse_at)(i),
+    |i| into(parse_keyframes)(i),
+    |i| into(parse_font_face)(i),
+    |i| into(parse_viewport)(i),
+    |i| into(parse_ms_viewport)(i),
+    |i| into(parse_supports)(i),
+    |i| into(parse_layer)(i),
+    |i| into(parse_page)(i),
+    |i| into(parse_block)(i),
+];
+
+/// Rest of the input at the furthest error of the entity parsers,
+/// it points to the invalid part of the entity
+pub(crate) fn entity_error(input: &str) -> Option<&str> {
+    ENTITY_PARSERS
+        .iter()
+        .filter_map(|parser| match parser(input) {
+            Err(Err::Error(e)) | Err(Err::Failure(e)) => Some(e.input),
+            _ => None,
+        })
+        .min_by_key(|rest| rest.len())
+}
+
+/// The first entity parsed by the parsers, otherwise the error of the last one
+pub fn parse_entity(input: &str) -> IResult<&str, CssEntity<'_>> {
+    let mut result = Err(Err::Error(Error::new(input, ErrorKind::Alt)));
+    for parser in ENTITY_PARSERS {
+        result = parser(input);
+        if !matches!(result, Err(Err::Error(_))) {
+            return result;
+        }
+    }
+    result
 }
@@ -0,0 +1,167 @@
+use nom::branch::alt;
+use nom::bytes::complete::{is_not, tag, take};
+use nom::character::complete::{char, multispace0};
+use nom::combinator::{all_consuming, map, map_opt, value};
+use nom::error::{Error, ErrorKind};
+use nom::multi::{many0, separated_list0};
+use nom::number::complete::double;
+use nom::sequence::{delimited, preceded, separated_pair, terminated};
+use nom::{Err, IResult};
+
+/// Json document, only what is needed to read source maps
+#[derive(Clone, PartialEq, Debug)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// Maximal nesting of arrays and objects, deeper documents are rejected
+const MAX_DEPTH: usize = 128;
+
+pub fn parse_json(input: &str) -> IResult<&str, Json> {
+    all_consuming(terminated(|i| parse_value(i, 0), multispace0))(input)
+}
+
+fn parse_value(input: &str, depth: usize) -> IResult<&str, Json> {
+    if depth > MAX_DEPTH {
+        return Err(Err::Failure(Error::new(input, ErrorKind::TooLarge)));
+    }
+    let nested = |i| parse_value(i, depth + 1);
+    preceded(
+        multispace0,
+        alt((
+            value(Json::Null, tag("null")),
+            value(Json::Bool(true), tag("true")),
+            value(Json::Bool(false), tag("false")),
+            map(double, Json::Number),
+            map(parse_string, Json::String),
+            map(
+                delimited(
+                    char('['),
+                    separated_list0(char(','), nested),
+                    preceded(multispace0, char(']')),
+                ),
+                Json::Array,
+            ),
+            map(
+                delimited(
+                    char('{'),
+                    separated_list0(
+                        char(','),
+                        separated_pair(
+                            preceded(multispace0, parse_string),
+                            preceded(multispace0, char(':')),
+                            nested,
+                        ),
+                    ),
+                    preceded(multispace0, char('}')),
+                ),
+                Json::Object,
+            ),
+        )),
+    )(input)
+}
+
+fn parse_string(input: &str) -> IResult<&str, String> {
+    map(
+        delimited(
+            char('"'),
+            many0(alt((
+                map(is_not("\"\\"), |s: &str| s.to_string()),
+                map(preceded(char('\\'), parse_escape), String::from),
+            ))),
+            char('"'),
+        ),
+        |parts| parts.concat(),
+    )(input)
+}
+
+fn parse_escape(input: &str) -> IResult<&str, char> {
+    alt((
+        value('"', char('"')),
+        value('\\', char('\\')),
+        value('/', char('/')),
+        value('\u{8}', char('b')),
+        value('\u{c}', char('f')),
+        value('\n', char('n')),
+        value('\r', char('r')),
+        value('\t', char('t')),
+        map_opt(
+            separated_pair(parse_unicode, char('\\'), parse_unicode),
+            |(high, low)| {
+                let high = high.checked_sub(0xd800).filter(|h| *h < 0x400)?;
+                let low = low.checked_sub(0xdc00).filter(|l| *l < 0x400)?;
+                char::from_u32(0x10000 + (high << 10) + low)
+            },
+        ),
+        map_opt(parse_unicode, char::from_u32),
+    ))(input)
+}
+
+fn parse_unicode(input: &str) -> IResult<&str, u32> {
+    map_opt(preceded(char('u'), take(4usize)), |hex: &str| {
+        u32::from_str_radix(hex, 16).ok()
+    })(input)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parsers::json::{parse_json, Json};
+
+    #[test]
+    fn test_json() {
+        assert_eq!(
+            parse_json(r#" { "version": 3, "sources": ["a.scss", null], "x": [ ], "ok": true } "#),
+            Ok((
+                "",
+                Json::Object(vec![
+                    ("version".into(), Json::Number(3.0)),
+                    (
+                        "sources".into(),
+                        Json::Array(vec![Json::String("a.scss".into()), Json::Null])
+                    ),
+                    ("x".into(), Json::Array(vec![])),
+                    ("ok".into(), Json::Bool(true)),
+                ])
+            ))
+        );
+        assert!(parse_json(r#"{"a": 1"#).is_err());
+        assert!(parse_json(&"[".repeat(100_000)).is_err());
+        assert!(parse_json(&format!("{}{}", "[".repeat(100), "]".repeat(100))).is_ok());
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(
+            parse_json(r#""a\n\"b\" ы \u00e9 \ud83d\ude00""#),
+            Ok(("", Json::String("a\n\"b\" ы é 😀".into())))
+        );
+    }
+}
@@ -1,6 +1,9 @@
 pub(crate) mod at;
 pub(crate) mod block;
 pub(crate) mod css_entity;
+pub(crate) mod json;
 pub(crate) mod parameters;
+pub(crate) mod recovery;
 pub(crate) mod selector;
 pub(crate) mod utils;
+pub(crate) mod value;
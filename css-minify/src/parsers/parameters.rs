@@ -1,6 +1,7 @@
-use crate::parsers::utils::{between, is_not_block_ending, non_useless, space};
-use crate::structure::{Name, Parameters, Value};
-use indexmap::map::IndexMap;
+use crate::parsers::utils::{
+    between, is_not_block_ending, located, non_useless, parenthesized, space,
+};
+use crate::structure::{Name, Parameter, Parameters, Value};
 use nom::branch::alt;
 use nom::bytes::complete::is_not;
 use nom::character::complete::char;
@@ -9,14 +10,24 @@ use nom::multi::{many0, many1};
 use nom::sequence::{separated_pair, terminated};
 use nom::IResult;
 
-pub fn parse_parameters(input: &str) -> IResult<&str, Parameters> {
+pub fn parse_parameters(input: &str) -> IResult<&str, Parameters<'_>> {
     map(
-        many0(non_useless(is_not_block_ending(parse_parameter))),
-        |p| p.into_iter().collect::<IndexMap<_, _>>().into(),
+        many0(non_useless(is_not_block_ending(parse_located_parameter))),
+        Parameters::from,
     )(input)
 }
 
-pub fn parse_parameter(input: &str) -> IResult<&str, (Name, Value)> {
+pub fn parse_located_parameter(input: &str) -> IResult<&str, Parameter<'_>> {
+    map(located(parse_parameter), |((name, value), location)| {
+        Parameter {
+            name,
+            value,
+            location,
+        }
+    })(input)
+}
+
+pub fn parse_parameter(input: &str) -> IResult<&str, (Name<'_>, Value<'_>)> {
     map(
         terminated(
             separated_pair(parse_key, char(':'), parse_value),
@@ -27,16 +38,16 @@ pub fn parse_parameter(input: &str) -> IResult<&str, (Name, Value)> {
 }
 
 fn parse_key(input: &str) -> IResult<&str, &str> {
-    non_useless(is_not(":;}"))(input)
+    non_useless(is_not(":;{}"))(input)
 }
 
 fn parse_value(input: &str) -> IResult<&str, &str> {
     non_useless(recognize(many1(alt((
         between("\"", "\""),
         between("'", "'"),
-        between("(", ")"),
+        parenthesized,
         space,
-        is_not("\"'();}"),
+        is_not("\"'();{}"),
     )))))(input)
 }
 
@@ -138,4 +149,25 @@ mod test {
             }))
         )
     }
+
+    #[test]
+    fn test_parameters_fallbacks() {
+        assert_eq!(
+            all_consuming(parse_parameters)(
+                "
+                display: -webkit-box;
+                display: flex;
+                "
+            ),
+            Ok((
+                "",
+                vec![
+                    ("display".into(), "-webkit-box".into()),
+                    ("display".into(), "flex".into()),
+                ]
+                .into_iter()
+                .collect()
+            ))
+        )
+    }
 }
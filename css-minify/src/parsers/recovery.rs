@@ -0,0 +1,145 @@
+//! Error recovery of the css syntax: how much of the input is skipped
+//! when a declaration or a rule is invalid
+
+/// Length of the invalid declaration, it ends after the next `;`, before the `}`
+/// which closes the enclosing block or after the block of the invalid nested rule
+pub fn skip_declaration(input: &str) -> usize {
+    skip(input, |c, depth| match c {
+        ';' if depth == 0 => Some(1),
+        '}' if depth == 0 => Some(0),
+        '}' if depth == 1 => Some(1),
+        _ => None,
+    })
+}
+
+/// Length of the invalid rule, it ends after its block or after the `;` of the at-rule statement.
+/// `}` without the opening `{` belongs to the prelude of the rule
+pub fn skip_rule(input: &str) -> usize {
+    let statement = input.starts_with('@');
+    skip(input, |c, depth| match c {
+        ';' if depth == 0 && statement => Some(1),
+        '}' if depth == 1 => Some(1),
+        _ => None,
+    })
+}
+
+/// Characters which close the string, the `url(`, brackets and blocks open at the end of the input,
+/// like the css syntax closes them at the end of the file. At-rule without the block is closed by `;`
+pub fn close_at_eof(input: &str) -> String {
+    let statement = input.starts_with('@') && !input.contains(['{', ';']);
+    let mut stack = vec![];
+    let mut quote = None;
+    let mut url = false;
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            c if quote == Some(c) => quote = None,
+            _ if quote.is_some() => {}
+            ')' if url => url = false,
+            _ if url => {}
+            '"' | '\'' => quote = Some(c),
+            '(' if input[..i].to_ascii_lowercase().ends_with("url")
+                && !input[i + 1..].trim_start().starts_with(['"', '\'']) =>
+            {
+                url = true
+            }
+            '{' | '(' | '[' => stack.push(c),
+            ')' | ']' | '}' => {
+                if let Some(position) = stack.iter().rposition(|&b| b == opening(c)) {
+                    stack.truncate(position);
+                }
+            }
+            _ => {}
+        }
+    }
+    let closing = stack.iter().rev().map(|&b| match b {
+        '{' => '}',
+        '(' => ')',
+        _ => ']',
+    });
+    quote
+        .into_iter()
+        .chain(url.then_some(')'))
+        .chain(closing)
+        .chain(statement.then_some(';'))
+        .collect()
+}
+
+/// Walks the input with nested blocks and strings, `stop` gets every top level character
+/// with the count of open `{}` blocks and returns how many bytes of it are taken to stop the walk
+fn skip(input: &str, mut stop: impl FnMut(char, usize) -> Option<usize>) -> usize {
+    let mut stack = vec![];
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        let depth = stack.iter().filter(|&&b| b == '{').count();
+        if stack.last().is_none_or(|&b| b == '{') {
+            if let Some(taken) = stop(c, depth) {
+                return i + taken;
+            }
+        }
+        match c {
+            '"' | '\'' => {
+                while let Some((_, s)) = chars.next() {
+                    match s {
+                        '\\' => {
+                            chars.next();
+                        }
+                        s if s == c => break,
+                        _ => {}
+                    }
+                }
+            }
+            '{' | '(' | '[' => stack.push(c),
+            ')' | ']' | '}' => {
+                if let Some(position) = stack.iter().rposition(|&b| b == opening(c)) {
+                    stack.truncate(position);
+                }
+            }
+            _ => {}
+        }
+    }
+    input.len()
+}
+
+fn opening(closing: char) -> char {
+    match closing {
+        ')' => '(',
+        ']' => '[',
+        _ => '{',
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parsers::recovery::{close_at_eof, skip_declaration, skip_rule};
+
+    #[test]
+    fn test_skip_declaration() {
+        assert_eq!(skip_declaration(";; color: red }"), 1);
+        assert_eq!(skip_declaration("color: \";}\" x; margin: 0"), 14);
+        assert_eq!(skip_declaration("color }"), 6);
+        assert_eq!(skip_declaration("b!! { color: red } c: d"), 18);
+    }
+
+    #[test]
+    fn test_skip_rule() {
+        assert_eq!(skip_rule("} b { color: red } c {}"), 18);
+        assert_eq!(skip_rule("@import 'a;b' x; a {}"), 16);
+        assert_eq!(skip_rule("a ( { ) { }"), 11);
+    }
+
+    #[test]
+    fn test_close_at_eof() {
+        assert_eq!(close_at_eof("a { b { c: d; } "), "}");
+        assert_eq!(close_at_eof("a { b: \"{\" }"), "");
+        assert_eq!(close_at_eof("a {} b { c {"), "}}");
+        assert_eq!(close_at_eof("a { content: \"abc"), "\"}");
+        assert_eq!(close_at_eof("a { b: url(x{"), ")}");
+        assert_eq!(close_at_eof("a { b: url('x"), "')}");
+        assert_eq!(close_at_eof("a { b: rgb(1 [2"), "])}");
+        assert_eq!(close_at_eof("@import url(x"), ");");
+    }
+}
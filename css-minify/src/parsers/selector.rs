@@ -1,94 +1,304 @@
-use crate::parsers::utils::{is_not_block_ending, non_useless};
-use crate::structure::{PseudoClass, Selector, SelectorWithPseudoClasses, Selectors};
+use crate::parsers::utils::{is_not_block_ending, non_useless, parse_useless};
+use crate::structure::{
+    Attribute, AttributeMatcher, AttributeOperator, Combinator, CombinatorKind, ComplexSelector,
+    CompoundSelector, PseudoArgument, PseudoClass, PseudoElement, Selector, Selectors,
+};
 use nom::branch::alt;
-use nom::bytes::complete::{is_a, is_not};
-use nom::character::complete::char;
-use nom::combinator::{map, opt};
-use nom::multi::{many0, separated_list1};
-use nom::sequence::{delimited, pair, preceded};
+use nom::bytes::complete::{is_not, tag, take_while1, take_while_m_n};
+use nom::character::complete::{anychar, char, one_of};
+use nom::combinator::{all_consuming, map, opt, recognize, verify};
+use nom::multi::{many0, many1, separated_list1};
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
 use nom::IResult;
 
-pub fn parse_selectors(input: &str) -> IResult<&str, Selectors> {
+/// Pseudo classes and elements which take a selector list as the argument
+const SELECTOR_ARGUMENTS: &[&str] = &[
+    "not",
+    "is",
+    "where",
+    "has",
+    "matches",
+    "-webkit-any",
+    "-moz-any",
+    "host",
+    "host-context",
+    "slotted",
+    "cue",
+];
+
+/// Pseudo elements which may be written with a single colon
+pub(crate) const LEGACY_PSEUDO_ELEMENTS: &[&str] =
+    &["before", "after", "first-line", "first-letter"];
+
+pub fn parse_selectors(input: &str) -> IResult<&str, Selectors<'_>> {
     map(
         is_not_block_ending(separated_list1(
             char(','),
-            non_useless(parse_selector_with_pseudo_class),
+            non_useless(parse_complex_selector),
         )),
         |selectors| selectors.into(),
     )(input)
 }
 
-pub fn parse_selector_with_pseudo_class(input: &str) -> IResult<&str, SelectorWithPseudoClasses> {
+pub fn parse_complex_selector(input: &str) -> IResult<&str, ComplexSelector<'_>> {
     map(
         pair(
-            non_useless(opt(parse_selector)),
-            non_useless(parse_pseudo_classes),
+            pair(opt(parse_leading_combinator), parse_compound_selector),
+            many0(pair(parse_combinator, parse_compound_selector)),
         ),
-        |(selector, pc)| SelectorWithPseudoClasses(selector, pc),
+        |((leading, first), rest)| {
+            let mut compounds = vec![(leading, first)];
+            compounds.extend(
+                rest.into_iter()
+                    .map(|(combinator, compound)| (Some(combinator), compound)),
+            );
+            compounds.into()
+        },
+    )(input)
+}
+
+/// Combinator of the relative selector: `> p`
+fn parse_leading_combinator(input: &str) -> IResult<&str, Combinator> {
+    map(
+        pair(one_of(">+~"), parse_useless),
+        |(symbol, after): (char, Vec<&str>)| Combinator {
+            kind: combinator_kind(symbol),
+            spaced: !after.is_empty(),
+        },
     )(input)
 }
 
-pub fn parse_pseudo_classes(input: &str) -> IResult<&str, Vec<PseudoClass>> {
-    many0(parse_pseudo_class)(input)
+pub fn parse_combinator(input: &str) -> IResult<&str, Combinator> {
+    alt((
+        map(
+            tuple((parse_useless, one_of(">+~"), parse_useless)),
+            |(before, symbol, after)| Combinator {
+                kind: combinator_kind(symbol),
+                spaced: !before.is_empty() || !after.is_empty(),
+            },
+        ),
+        // a comment alone separates the compounds like the whitespace
+        map(
+            verify(parse_useless, |useless: &[&str]| !useless.is_empty()),
+            |_| Combinator {
+                kind: CombinatorKind::Descendant,
+                spaced: true,
+            },
+        ),
+    ))(input)
+}
+
+fn combinator_kind(symbol: char) -> CombinatorKind {
+    match symbol {
+        '>' => CombinatorKind::Child,
+        '+' => CombinatorKind::NextSibling,
+        _ => CombinatorKind::SubsequentSibling,
+    }
+}
+
+pub fn parse_compound_selector(input: &str) -> IResult<&str, CompoundSelector<'_>> {
+    map(many1(parse_selector), |selectors| selectors.into())(input)
+}
+
+pub fn parse_selector(input: &str) -> IResult<&str, Selector<'_>> {
+    alt((
+        parse_id,
+        parse_class,
+        parse_attribute,
+        parse_pseudo,
+        parse_nesting,
+        parse_tag,
+    ))(input)
+}
+
+pub fn parse_id(input: &str) -> IResult<&str, Selector<'_>> {
+    map(preceded(char('#'), parse_name), |i: &str| {
+        Selector::Id(i.into())
+    })(input)
+}
+
+pub fn parse_class(input: &str) -> IResult<&str, Selector<'_>> {
+    map(preceded(char('.'), parse_name), |i: &str| {
+        Selector::Class(i.into())
+    })(input)
+}
+
+pub fn parse_tag(input: &str) -> IResult<&str, Selector<'_>> {
+    map(parse_qualified_name(parse_type_name), |i| {
+        Selector::Tag(i.into())
+    })(input)
 }
 
-pub fn parse_pseudo_class(input: &str) -> IResult<&str, PseudoClass> {
+fn parse_type_name(input: &str) -> IResult<&str, &str> {
+    alt((tag("*"), parse_name))(input)
+}
+
+fn parse_nesting(input: &str) -> IResult<&str, Selector<'_>> {
+    map(char('&'), |_| Selector::Nesting)(input)
+}
+
+pub fn parse_attribute(input: &str) -> IResult<&str, Selector<'_>> {
     map(
-        pair(
-            pair(
-                non_useless(parse_pseudo_class_name),
-                non_useless(opt(parse_pseudo_class_params)),
-            ),
-            map(non_useless(opt(is_not(",{:"))), |i: Option<&str>| {
-                i.map(|i| i.to_string())
-            }),
+        delimited(
+            char('['),
+            non_useless(pair(
+                parse_qualified_name(parse_name),
+                opt(tuple((
+                    non_useless(parse_attribute_operator),
+                    parse_attribute_value,
+                    opt(preceded(parse_useless, one_of("iIsS"))),
+                ))),
+            )),
+            char(']'),
         ),
-        |(((prefix, name), params), next)| PseudoClass {
-            prefix,
-            name,
-            params,
-            next,
+        |(name, matcher)| {
+            Selector::Attribute(Attribute {
+                name: name.into(),
+                matcher: matcher.map(|(operator, (value, quote), flag)| AttributeMatcher {
+                    operator,
+                    value: value.into(),
+                    quote,
+                    flag,
+                }),
+            })
         },
     )(input)
 }
 
-pub fn parse_pseudo_class_name(input: &str) -> IResult<&str, (String, String)> {
-    map(
-        pair(is_a(":"), is_not("(,{:")),
-        |(prefix, name): (&str, &str)| (prefix.to_string(), name.to_string()),
-    )(input)
+fn parse_attribute_operator(input: &str) -> IResult<&str, AttributeOperator> {
+    alt((
+        map(tag("~="), |_| AttributeOperator::Includes),
+        map(tag("|="), |_| AttributeOperator::DashMatch),
+        map(tag("^="), |_| AttributeOperator::Prefix),
+        map(tag("$="), |_| AttributeOperator::Suffix),
+        map(tag("*="), |_| AttributeOperator::Substring),
+        map(tag("="), |_| AttributeOperator::Equal),
+    ))(input)
 }
 
-pub fn parse_pseudo_class_params(input: &str) -> IResult<&str, String> {
-    map(delimited(char('('), is_not(")"), char(')')), |i: &str| {
-        i.to_string()
-    })(input)
+fn parse_attribute_value(input: &str) -> IResult<&str, (&str, Option<char>)> {
+    alt((
+        map(parse_string('"'), |value| (value, Some('"'))),
+        map(parse_string('\''), |value| (value, Some('\''))),
+        map(parse_name, |value| (value, None)),
+    ))(input)
 }
 
-pub fn parse_selector(input: &str) -> IResult<&str, Selector> {
-    alt((parse_id, parse_class, parse_tag))(input)
+pub fn parse_pseudo(input: &str) -> IResult<&str, Selector<'_>> {
+    alt((
+        map(
+            preceded(tag("::"), pair(parse_name, opt(parse_pseudo_argument))),
+            |(name, argument)| {
+                Selector::PseudoElement(PseudoElement {
+                    name: name.into(),
+                    argument: argument.map(|argument| pseudo_argument(name, argument)),
+                    legacy: false,
+                })
+            },
+        ),
+        map(
+            preceded(char(':'), pair(parse_name, opt(parse_pseudo_argument))),
+            |(name, argument)| {
+                let argument = argument.map(|argument| pseudo_argument(name, argument));
+                if LEGACY_PSEUDO_ELEMENTS.contains(&name.to_lowercase().as_str()) {
+                    Selector::PseudoElement(PseudoElement {
+                        name: name.into(),
+                        argument,
+                        legacy: true,
+                    })
+                } else {
+                    Selector::PseudoClass(PseudoClass {
+                        name: name.into(),
+                        argument,
+                    })
+                }
+            },
+        ),
+    ))(input)
 }
 
-pub fn parse_id(input: &str) -> IResult<&str, Selector> {
-    map(preceded(char('#'), is_not(",{:")), |i: &str| {
-        Selector::Id(i.trim().into())
-    })(input)
+fn parse_pseudo_argument(input: &str) -> IResult<&str, &str> {
+    delimited(char('('), parse_balanced, char(')'))(input)
 }
 
-pub fn parse_class(input: &str) -> IResult<&str, Selector> {
-    map(preceded(char('.'), is_not(",{:")), |i: &str| {
-        Selector::Class(i.trim().into())
-    })(input)
+fn pseudo_argument<'a>(name: &str, argument: &'a str) -> PseudoArgument<'a> {
+    if SELECTOR_ARGUMENTS.contains(&name.to_lowercase().as_str()) {
+        if let Ok((_, selectors)) = all_consuming(non_useless(parse_selectors))(argument) {
+            return selectors.into();
+        }
+    }
+    PseudoArgument::Raw(argument.trim().into())
+}
+
+/// Content between the parentheses with nested parentheses and strings
+fn parse_balanced(input: &str) -> IResult<&str, &str> {
+    recognize(many0(alt((
+        is_not("()\"'"),
+        recognize(parse_string('"')),
+        recognize(parse_string('\'')),
+        recognize(delimited(char('('), parse_balanced, char(')'))),
+    ))))(input)
+}
+
+/// Name with the optional namespace prefix: `svg|a`, `*|a`, `|a`
+fn parse_qualified_name<'a, P>(name: P) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str>
+where
+    P: FnMut(&'a str) -> IResult<&'a str, &'a str> + Copy,
+{
+    alt((
+        recognize(pair(
+            terminated(opt(alt((tag("*"), parse_name))), char('|')),
+            name,
+        )),
+        name,
+    ))
 }
 
-pub fn parse_tag(input: &str) -> IResult<&str, Selector> {
-    map(is_not(",{:"), |i: &str| Selector::Tag(i.trim().into()))(input)
+/// Css identifier characters with escapes: `md\:flex`, `\31 0`
+pub fn parse_name(input: &str) -> IResult<&str, &str> {
+    recognize(many1(alt((
+        take_while1(|c: char| c.is_alphanumeric() || c == '-' || c == '_' || !c.is_ascii()),
+        recognize(pair(
+            char('\\'),
+            alt((
+                recognize(pair(
+                    take_while_m_n(1, 6, |c: char| c.is_ascii_hexdigit()),
+                    opt(one_of(" \t\n")),
+                )),
+                recognize(anychar),
+            )),
+        )),
+    ))))(input)
+}
+
+/// Quoted string content without the quotes
+pub(crate) fn parse_string<'a>(quote: char) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str> {
+    move |input| {
+        delimited(
+            char(quote),
+            recognize(many0(alt((
+                take_while1(|c: char| c != quote && c != '\\'),
+                recognize(pair(char('\\'), anychar)),
+            )))),
+            char(quote),
+        )(input)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use crate::parsers::selector::{parse_selector, parse_selectors};
-    use crate::structure::{PseudoClass, Selector, SelectorWithPseudoClasses};
+    use crate::structure::{
+        Attribute, AttributeMatcher, AttributeOperator, Combinator, CombinatorKind,
+        ComplexSelector, CompoundSelector, PseudoArgument, PseudoClass, PseudoElement, Selector,
+    };
+
+    fn pseudo_class<'a>(name: &'a str, argument: Option<PseudoArgument<'a>>) -> Selector<'a> {
+        Selector::PseudoClass(PseudoClass {
+            name: name.into(),
+            argument,
+        })
+    }
 
     #[test]
     fn test_selector() {
@@ -104,6 +314,14 @@ mod test {
             parse_selector("input"),
             Ok(("", Selector::Tag("input".into())))
         );
+        assert_eq!(
+            parse_selector(r".md\:flex"),
+            Ok(("", Selector::Class(r"md\:flex".into())))
+        );
+        assert_eq!(
+            parse_selector("svg|a"),
+            Ok(("", Selector::Tag("svg|a".into())))
+        );
     }
 
     #[test]
@@ -113,9 +331,9 @@ mod test {
             Ok((
                 "",
                 vec![
-                    SelectorWithPseudoClasses(Some(Selector::Id("some_id".into())), vec![]),
-                    SelectorWithPseudoClasses(Some(Selector::Class("some_class".into())), vec![]),
-                    SelectorWithPseudoClasses(Some(Selector::Tag("input".into())), vec![]),
+                    Selector::Id("some_id".into()).into(),
+                    Selector::Class("some_class".into()).into(),
+                    Selector::Tag("input".into()).into(),
                 ]
                 .into()
             ))
@@ -128,15 +346,10 @@ mod test {
             parse_selectors("#some_id:only-child"),
             Ok((
                 "",
-                vec![SelectorWithPseudoClasses(
-                    Some(Selector::Id("some_id".into())),
-                    vec![PseudoClass {
-                        prefix: ":".to_string(),
-                        name: "only-child".to_string(),
-                        params: None,
-                        next: None,
-                    }],
-                ),]
+                vec![ComplexSelector::from(CompoundSelector(vec![
+                    Selector::Id("some_id".into()),
+                    pseudo_class("only-child", None),
+                ]))]
                 .into()
             ))
         );
@@ -148,15 +361,10 @@ mod test {
             parse_selectors("#some_id:nth-child(4n)"),
             Ok((
                 "",
-                vec![SelectorWithPseudoClasses(
-                    Some(Selector::Id("some_id".into())),
-                    vec![PseudoClass {
-                        prefix: ":".to_string(),
-                        name: "nth-child".to_string(),
-                        params: Some("4n".to_string()),
-                        next: None,
-                    }],
-                ),]
+                vec![ComplexSelector::from(CompoundSelector(vec![
+                    Selector::Id("some_id".into()),
+                    pseudo_class("nth-child", Some(PseudoArgument::Raw("4n".into()))),
+                ]))]
                 .into()
             ))
         );
@@ -168,15 +376,17 @@ mod test {
             parse_selectors(":is(nav, .posts)"),
             Ok((
                 "",
-                vec![SelectorWithPseudoClasses(
-                    None,
-                    vec![PseudoClass {
-                        prefix: ":".to_string(),
-                        name: "is".to_string(),
-                        params: Some("nav, .posts".to_string()),
-                        next: None,
-                    }],
-                ),]
+                vec![pseudo_class(
+                    "is",
+                    Some(PseudoArgument::Selectors(
+                        vec![
+                            Selector::Tag("nav".into()).into(),
+                            Selector::Class("posts".into()).into(),
+                        ]
+                        .into()
+                    ))
+                )
+                .into()]
                 .into()
             ))
         );
@@ -188,35 +398,53 @@ mod test {
             parse_selectors(":is(.test) a"),
             Ok((
                 "",
-                vec![SelectorWithPseudoClasses(
-                    None,
-                    vec![PseudoClass {
-                        prefix: ":".to_string(),
-                        name: "is".to_string(),
-                        params: Some(".test".to_string()),
-                        next: Some("a".to_string()),
-                    }],
-                ),]
+                vec![ComplexSelector(vec![
+                    (
+                        None,
+                        CompoundSelector(vec![pseudo_class(
+                            "is",
+                            Some(PseudoArgument::Selectors(
+                                vec![Selector::Class("test".into()).into()].into()
+                            ))
+                        )])
+                    ),
+                    (
+                        Some(Combinator {
+                            kind: CombinatorKind::Descendant,
+                            spaced: true
+                        }),
+                        CompoundSelector(vec![Selector::Tag("a".into())])
+                    ),
+                ])]
                 .into()
             ))
         );
     }
 
     #[test]
-    fn test_pc_double_dots() {
+    fn test_pseudo_elements() {
         assert_eq!(
-            parse_selectors("::is(.test) a"),
+            parse_selectors("a::before, a:after"),
             Ok((
                 "",
-                vec![SelectorWithPseudoClasses(
-                    None,
-                    vec![PseudoClass {
-                        prefix: "::".to_string(),
-                        name: "is".to_string(),
-                        params: Some(".test".to_string()),
-                        next: Some("a".to_string()),
-                    }],
-                ),]
+                vec![
+                    ComplexSelector::from(CompoundSelector(vec![
+                        Selector::Tag("a".into()),
+                        Selector::PseudoElement(PseudoElement {
+                            name: "before".into(),
+                            argument: None,
+                            legacy: false,
+                        }),
+                    ])),
+                    ComplexSelector::from(CompoundSelector(vec![
+                        Selector::Tag("a".into()),
+                        Selector::PseudoElement(PseudoElement {
+                            name: "after".into(),
+                            argument: None,
+                            legacy: true,
+                        }),
+                    ])),
+                ]
                 .into()
             ))
         );
@@ -228,25 +456,98 @@ mod test {
             parse_selectors("a:not([href]):not([tabindex])"),
             Ok((
                 "",
-                vec![SelectorWithPseudoClasses(
-                    Some(Selector::Tag("a".into())),
-                    vec![
-                        PseudoClass {
-                            prefix: ":".to_string(),
-                            name: "not".to_string(),
-                            params: Some("[href]".to_string()),
-                            next: None,
-                        },
-                        PseudoClass {
-                            prefix: ":".to_string(),
-                            name: "not".to_string(),
-                            params: Some("[tabindex]".to_string()),
-                            next: None,
-                        },
-                    ],
-                ),]
+                vec![ComplexSelector::from(CompoundSelector(vec![
+                    Selector::Tag("a".into()),
+                    pseudo_class(
+                        "not",
+                        Some(PseudoArgument::Selectors(
+                            vec![Selector::Attribute(Attribute {
+                                name: "href".into(),
+                                matcher: None,
+                            })
+                            .into()]
+                            .into()
+                        ))
+                    ),
+                    pseudo_class(
+                        "not",
+                        Some(PseudoArgument::Selectors(
+                            vec![Selector::Attribute(Attribute {
+                                name: "tabindex".into(),
+                                matcher: None,
+                            })
+                            .into()]
+                            .into()
+                        ))
+                    ),
+                ]))]
+                .into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_complex_selector() {
+        assert_eq!(
+            parse_selectors(r#"div > p.foo[data-x="a b" i]"#),
+            Ok((
+                "",
+                vec![ComplexSelector(vec![
+                    (None, CompoundSelector(vec![Selector::Tag("div".into())])),
+                    (
+                        Some(Combinator {
+                            kind: CombinatorKind::Child,
+                            spaced: true
+                        }),
+                        CompoundSelector(vec![
+                            Selector::Tag("p".into()),
+                            Selector::Class("foo".into()),
+                            Selector::Attribute(Attribute {
+                                name: "data-x".into(),
+                                matcher: Some(AttributeMatcher {
+                                    operator: AttributeOperator::Equal,
+                                    value: "a b".into(),
+                                    quote: Some('"'),
+                                    flag: Some('i'),
+                                }),
+                            }),
+                        ])
+                    ),
+                ])]
                 .into()
             ))
         );
     }
+
+    #[test]
+    fn test_descendant_with_comments() {
+        for selector in ["a /* x */ b", "a/**/b", "a/* x */ /* y */b"] {
+            assert_eq!(
+                parse_selectors(selector).map(|(rest, s)| (rest, s.to_string())),
+                Ok(("", "a b".to_string()))
+            );
+        }
+        assert_eq!(
+            parse_selectors("a /* x */ > b").map(|(rest, s)| (rest, s.to_string())),
+            Ok(("", "a > b".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_selectors_display() {
+        for selector in [
+            "div > p.foo[data-x=\"a b\" i]",
+            "a+b~c>d e",
+            "[lang|=en]:not(.a,.b)::before",
+            "> p",
+            "& + &:hover",
+            "li:nth-child(2n + 1 of .item)",
+            ":has(> img)",
+        ] {
+            assert_eq!(
+                parse_selectors(selector).map(|(rest, s)| (rest, s.to_string())),
+                Ok(("", selector.to_string()))
+            );
+        }
+    }
 }
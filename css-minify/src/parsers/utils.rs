@@ -1,9 +1,10 @@
+use crate::structure::Location;
 use nom::branch::alt;
 use nom::bytes::complete::{is_a, is_not, tag, take_until};
 use nom::character::complete::multispace1;
 use nom::character::complete::{char, none_of};
 use nom::combinator::{map, peek};
-use nom::error::Error as IError;
+use nom::error::{Error as IError, ErrorKind};
 use nom::multi::many0;
 use nom::sequence::{delimited, preceded, tuple};
 use nom::{IResult, Parser};
@@ -14,6 +15,19 @@ pub fn non_useless<'a, O, P: Parser<&'a str, O, IError<&'a str>>>(
     map(tuple((parse_useless, parser, parse_useless)), |(_, r, _)| r)
 }
 
+/// Output of the parser with the location of its start, which is kept as
+/// the remaining length of the input till the tree is relocated after parsing
+pub fn located<'a, O, P: Parser<&'a str, O, IError<&'a str>>>(
+    mut parser: P,
+) -> impl FnMut(&'a str) -> IResult<&'a str, (O, Location), IError<&'a str>> {
+    move |input: &'a str| {
+        let location = Location(Some(input.len()));
+        parser
+            .parse(input)
+            .map(|(input, output)| (input, (output, location)))
+    }
+}
+
 pub fn parse_useless(input: &str) -> IResult<&str, Vec<&str>> {
     many0(alt((multispace1, parse_comment)))(input)
 }
@@ -77,9 +91,32 @@ pub fn between<'a>(
     delimited(tag(opener), take_until(closer), tag(closer))
 }
 
+/// Parenthesized part of the value with nested parentheses and strings: `(#fff, rgba(0,0,0,0))`
+pub fn parenthesized(input: &str) -> IResult<&str, &str> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            _ if i == 0 && c != '(' => break,
+            '\\' => {
+                chars.next();
+            }
+            c if quote == Some(c) => quote = None,
+            _ if quote.is_some() => {}
+            '"' | '\'' => quote = Some(c),
+            '(' => depth += 1,
+            ')' if depth == 1 => return Ok((&input[i + 1..], &input[..=i])),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    Err(nom::Err::Error(IError::new(input, ErrorKind::Char)))
+}
+
 #[cfg(test)]
 mod test {
-    use crate::parsers::utils::parse_comment;
+    use crate::parsers::utils::{parenthesized, parse_comment};
 
     #[test]
     fn test_comment() {
@@ -88,4 +125,15 @@ mod test {
             Ok(("", " ***MEGA COMMENT*** "))
         )
     }
+
+    #[test]
+    fn test_parenthesized() {
+        assert_eq!(
+            parenthesized("(#fff, rgba(0,0,0,0)) x"),
+            Ok((" x", "(#fff, rgba(0,0,0,0))"))
+        );
+        assert_eq!(parenthesized("(\")\") x"), Ok((" x", "(\")\")")));
+        assert!(parenthesized("(a(b)").is_err());
+        assert!(parenthesized("a(b)").is_err());
+    }
 }
@@ -0,0 +1,199 @@
+use crate::parsers::selector::{parse_name, parse_string};
+use crate::parsers::utils::parse_comment;
+use crate::structure::{ComponentValue, ComponentValues};
+use nom::branch::alt;
+use nom::bytes::complete::{is_not, tag_no_case};
+use nom::character::complete::{anychar, char, digit1, multispace0, multispace1, one_of};
+use nom::combinator::{map, not, opt, peek, recognize, value, verify};
+use nom::multi::{many0, many1};
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
+use nom::IResult;
+
+/// Split the declaration value into component values, it never fails:
+/// unknown characters are kept as delimiters
+pub fn parse_component_values(input: &str) -> ComponentValues {
+    let mut values = vec![];
+    let mut rest = input;
+    while let Ok((next, value)) = parse_component_value(rest, None) {
+        values.push(value);
+        rest = next;
+    }
+    ComponentValues(values)
+}
+
+/// Component value in the brackets closed by `close`
+fn parse_component_value(input: &str, close: Option<char>) -> IResult<&str, ComponentValue> {
+    alt((
+        value(
+            ComponentValue::Whitespace,
+            many1(alt((multispace1, parse_comment))),
+        ),
+        map(
+            recognize(alt((parse_string('"'), parse_string('\'')))),
+            |s: &str| ComponentValue::String(s.into()),
+        ),
+        parse_url,
+        parse_function,
+        parse_numeric,
+        map(parse_ident, |ident| ComponentValue::Ident(ident.into())),
+        map(preceded(char('#'), parse_name), |hash| {
+            ComponentValue::Hash(hash.into())
+        }),
+        value(ComponentValue::Comma, char(',')),
+        parse_block,
+        map(
+            verify(anychar, |c| Some(*c) != close),
+            ComponentValue::Delim,
+        ),
+    ))(input)
+}
+
+fn parse_block(input: &str) -> IResult<&str, ComponentValue> {
+    let (input, open) = one_of("([")(input)?;
+    let close = if open == '[' { ']' } else { ')' };
+    let (input, content) = parse_nested_until(input, close)?;
+    Ok((input, ComponentValue::Block(open, content)))
+}
+
+/// Content of the function or the block after its opening bracket,
+/// the closing bracket may be missing at the end of the input
+fn parse_nested_until(input: &str, close: char) -> IResult<&str, ComponentValues> {
+    map(
+        terminated(
+            many0(|i| parse_component_value(i, Some(close))),
+            opt(char(close)),
+        ),
+        ComponentValues,
+    )(input)
+}
+
+fn parse_function(input: &str) -> IResult<&str, ComponentValue> {
+    let (input, name) = terminated(parse_ident, char('('))(input)?;
+    let (input, arguments) = parse_nested_until(input, ')')?;
+    Ok((input, ComponentValue::Function(name.into(), arguments)))
+}
+
+fn parse_url(input: &str) -> IResult<&str, ComponentValue> {
+    map(
+        delimited(
+            tag_no_case("url("),
+            delimited(
+                multispace0,
+                preceded(not(one_of("\"'")), recognize(opt(is_not(")")))),
+                multispace0,
+            ),
+            char(')'),
+        ),
+        |url: &str| ComponentValue::Url(url.trim_end().into()),
+    )(input)
+}
+
+fn parse_numeric(input: &str) -> IResult<&str, ComponentValue> {
+    let (input, number) = parse_number(input)?;
+    alt((
+        value(ComponentValue::Percentage(number.into()), char('%')),
+        map(parse_name, |unit| {
+            ComponentValue::Dimension(number.into(), unit.into())
+        }),
+        |i| Ok((i, ComponentValue::Number(number.into()))),
+    ))(input)
+}
+
+/// Number with the optional sign and exponent: `-1.5e3`
+pub fn parse_number(input: &str) -> IResult<&str, &str> {
+    recognize(tuple((
+        opt(one_of("+-")),
+        alt((
+            recognize(pair(digit1, opt(pair(char('.'), digit1)))),
+            recognize(pair(char('.'), digit1)),
+        )),
+        opt(tuple((one_of("eE"), opt(one_of("+-")), digit1))),
+    )))(input)
+}
+
+fn parse_ident(input: &str) -> IResult<&str, &str> {
+    recognize(pair(
+        opt(char('-')),
+        preceded(peek(not(one_of("0123456789"))), parse_name),
+    ))(input)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parsers::value::parse_component_values;
+    use crate::structure::ComponentValue::*;
+    use crate::structure::ComponentValues;
+
+    fn values(values: Vec<crate::structure::ComponentValue>) -> ComponentValues {
+        ComponentValues(values)
+    }
+
+    #[test]
+    fn test_component_values() {
+        assert_eq!(
+            parse_component_values("1px solid #FFF !important"),
+            values(vec![
+                Dimension("1".into(), "px".into()),
+                Whitespace,
+                Ident("solid".into()),
+                Whitespace,
+                Hash("FFF".into()),
+                Whitespace,
+                Delim('!'),
+                Ident("important".into()),
+            ])
+        );
+        assert_eq!(
+            parse_component_values("calc(-0.5em + 10%) , 1e3/2"),
+            values(vec![
+                Function(
+                    "calc".into(),
+                    values(vec![
+                        Dimension("-0.5".into(), "em".into()),
+                        Whitespace,
+                        Delim('+'),
+                        Whitespace,
+                        Percentage("10".into()),
+                    ])
+                ),
+                Whitespace,
+                Comma,
+                Whitespace,
+                Number("1e3".into()),
+                Delim('/'),
+                Number("2".into()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_component_values_strings() {
+        assert_eq!(
+            parse_component_values(r#""a, b" url( a.png ) url("b.png") --x"#),
+            values(vec![
+                String(r#""a, b""#.into()),
+                Whitespace,
+                Url("a.png".into()),
+                Whitespace,
+                Function("url".into(), values(vec![String(r#""b.png""#.into())])),
+                Whitespace,
+                Ident("--x".into()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_component_values_roundtrip() {
+        for (value, expected) in [
+            ("U+0025-00FF", "U+0025-00FF"),
+            ("[full-start] 1fr [main]", "[full-start] 1fr [main]"),
+            ("var(--a,)", "var(--a,)"),
+            ("-webkit-calc(1px - -2px)", "-webkit-calc(1px - -2px)"),
+            ("a /* b */\n c", "a c"),
+            ("f( ] )", "f( ] )"),
+            ("f(a", "f(a)"),
+        ] {
+            assert_eq!(parse_component_values(value).to_string(), expected);
+        }
+    }
+}
@@ -0,0 +1,701 @@
+//! Source maps (revision 3) which point every rule and declaration
+//! of the minified css back to its position in the input
+
+use crate::parsers::json::{parse_json, Json};
+use crate::structure::{CssWriter, Location, Stylesheet, WriteCss};
+use std::error::Error;
+use std::fmt::{Display, Formatter, Write};
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Mapping of the generated position to the original one,
+/// lines and columns start from 0 and columns are counted in UTF-16 code units
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Mapping {
+    pub generated_line: usize,
+    pub generated_column: usize,
+    /// Index in the `sources` of the map
+    pub source: usize,
+    pub original_line: usize,
+    pub original_column: usize,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct SourceMap {
+    pub file: Option<String>,
+    pub sources: Vec<String>,
+    pub sources_content: Vec<Option<String>>,
+    /// Sorted by the generated position
+    pub mappings: Vec<Mapping>,
+}
+
+/// How the minified css refers to its source map
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub enum SourceMapUrl {
+    /// Without the `sourceMappingURL` comment
+    #[default]
+    None,
+    /// Source map embedded into the comment as the data url
+    Inline,
+    /// Url of the separate map file
+    External(String),
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct SourceMapOptions {
+    /// Name of the input in the `sources` of the map
+    pub source: String,
+    /// Name of the minified file
+    pub file: Option<String>,
+    /// Embed the input into `sourcesContent`
+    pub include_content: bool,
+    pub url: SourceMapUrl,
+    /// Map of the input produced by a preprocessor, the result points to its sources
+    pub input_map: Option<SourceMap>,
+}
+
+/// Source map which can't be read
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum ParseSourceMapError {
+    InvalidJson,
+    /// Only the revision 3 without sections is supported
+    UnsupportedVersion,
+    InvalidMappings,
+}
+
+impl Display for ParseSourceMapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseSourceMapError::InvalidJson => write!(f, "Source map is not a valid json"),
+            ParseSourceMapError::UnsupportedVersion => {
+                write!(
+                    f,
+                    "Only source maps of version 3 without sections are supported"
+                )
+            }
+            ParseSourceMapError::InvalidMappings => write!(f, "Source map has invalid mappings"),
+        }
+    }
+}
+
+impl Error for ParseSourceMapError {}
+
+impl SourceMap {
+    /// Read the source map json, `sourceRoot` is prepended to the sources
+    pub fn from_json(json: &str) -> Result<Self, ParseSourceMapError> {
+        let (_, json) = parse_json(json).map_err(|_| ParseSourceMapError::InvalidJson)?;
+        if json.get("version") != Some(&Json::Number(3.0)) || json.get("sections").is_some() {
+            return Err(ParseSourceMapError::UnsupportedVersion);
+        }
+        let root = json
+            .get("sourceRoot")
+            .and_then(Json::as_str)
+            .filter(|root| !root.is_empty())
+            .map(|root| match root.ends_with('/') {
+                true => root.to_string(),
+                false => format!("{}/", root),
+            })
+            .unwrap_or_default();
+        let strings = |key| -> Vec<Option<String>> {
+            json.get(key)
+                .and_then(Json::as_array)
+                .unwrap_or_default()
+                .iter()
+                .map(|s| s.as_str().map(String::from))
+                .collect()
+        };
+        let sources = strings("sources")
+            .into_iter()
+            .map(|source| format!("{}{}", root, source.unwrap_or_default()))
+            .collect::<Vec<_>>();
+        let mut sources_content = strings("sourcesContent");
+        sources_content.resize(sources.len(), None);
+        let mappings = json
+            .get("mappings")
+            .and_then(Json::as_str)
+            .ok_or(ParseSourceMapError::InvalidMappings)?;
+        let mappings = decode_mappings(mappings)
+            .filter(|mappings| mappings.iter().all(|m| m.source < sources.len()))
+            .ok_or(ParseSourceMapError::InvalidMappings)?;
+        Ok(SourceMap {
+            file: json.get("file").and_then(Json::as_str).map(String::from),
+            sources,
+            sources_content,
+            mappings,
+        })
+    }
+
+    /// Original position of the generated one, it is the closest mapping
+    /// at the same line which starts before the column
+    pub fn lookup(&self, line: usize, column: usize) -> Option<&Mapping> {
+        let end = self
+            .mappings
+            .partition_point(|m| (m.generated_line, m.generated_column) <= (line, column));
+        self.mappings[..end]
+            .last()
+            .filter(|m| m.generated_line == line)
+    }
+
+    /// Trace the mappings of this map through the map of its input,
+    /// the positions which are not mapped by the input map are dropped
+    pub fn compose(&self, input_map: &SourceMap) -> SourceMap {
+        let mappings = self
+            .mappings
+            .iter()
+            .filter_map(|m| {
+                let original = input_map.lookup(m.original_line, m.original_column)?;
+                Some(Mapping {
+                    source: original.source,
+                    original_line: original.original_line,
+                    original_column: original.original_column,
+                    ..*m
+                })
+            })
+            .collect();
+        SourceMap {
+            file: self.file.clone(),
+            sources: input_map.sources.clone(),
+            sources_content: input_map.sources_content.clone(),
+            mappings,
+        }
+    }
+
+    /// Mappings encoded as base64 VLQ segments
+    pub fn encode_mappings(&self) -> String {
+        let mut encoded = String::new();
+        let (mut line, mut column) = (0, 0);
+        let (mut source, mut original_line, mut original_column) = (0, 0, 0);
+        for (i, mapping) in self.mappings.iter().enumerate() {
+            if mapping.generated_line != line {
+                (line..mapping.generated_line).for_each(|_| encoded.push(';'));
+                line = mapping.generated_line;
+                column = 0;
+            } else if i > 0 {
+                encoded.push(',');
+            }
+            encode_vlq(&mut encoded, mapping.generated_column, column);
+            encode_vlq(&mut encoded, mapping.source, source);
+            encode_vlq(&mut encoded, mapping.original_line, original_line);
+            encode_vlq(&mut encoded, mapping.original_column, original_column);
+            column = mapping.generated_column;
+            source = mapping.source;
+            original_line = mapping.original_line;
+            original_column = mapping.original_column;
+        }
+        encoded
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"version\":3");
+        if let Some(file) = &self.file {
+            json.push_str(",\"file\":");
+            push_json_string(&mut json, file);
+        }
+        json.push_str(",\"sources\":[");
+        for (i, source) in self.sources.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            push_json_string(&mut json, source);
+        }
+        json.push(']');
+        if self.sources_content.iter().any(Option::is_some) {
+            json.push_str(",\"sourcesContent\":[");
+            for (i, content) in self.sources_content.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                match content {
+                    Some(content) => push_json_string(&mut json, content),
+                    None => json.push_str("null"),
+                }
+            }
+            json.push(']');
+        }
+        json.push_str(",\"names\":[],\"mappings\":\"");
+        json.push_str(&self.encode_mappings());
+        json.push_str("\"}");
+        json
+    }
+
+    /// Source map as the base64 data url for the inline `sourceMappingURL`
+    pub fn to_data_url(&self) -> String {
+        format!(
+            "data:application/json;charset=utf-8;base64,{}",
+            encode_base64(self.to_json().as_bytes())
+        )
+    }
+}
+
+/// Serialize the stylesheet parsed from the input together with its source map,
+/// the `sourceMappingURL` comment is appended according to the options
+pub fn write_with_source_map(
+    stylesheet: &Stylesheet,
+    input: &str,
+    options: &SourceMapOptions,
+) -> (String, SourceMap) {
+    let mut writer = SourceMapWriter::new(input);
+    stylesheet
+        .write_css(&mut writer)
+        .expect("writing into a string never fails");
+    let mut source_map = SourceMap {
+        file: options.file.clone(),
+        sources: vec![options.source.clone()],
+        sources_content: vec![options.include_content.then(|| input.to_string())],
+        mappings: writer.mappings,
+    };
+    source_map
+        .mappings
+        .sort_by_key(|m| (m.generated_line, m.generated_column));
+    if let Some(input_map) = &options.input_map {
+        source_map = source_map.compose(input_map);
+    }
+    let mut css = writer.output;
+    match &options.url {
+        SourceMapUrl::None => {}
+        SourceMapUrl::Inline => {
+            css.push_str(&format!(
+                "/*# sourceMappingURL={} */",
+                source_map.to_data_url()
+            ));
+        }
+        SourceMapUrl::External(url) => {
+            css.push_str(&format!("/*# sourceMappingURL={} */", url));
+        }
+    }
+    (css, source_map)
+}
+
+/// Writer which records the generated position of every marked location
+struct SourceMapWriter<'a> {
+    input: &'a str,
+    /// Offsets of the input lines and whether the line is ASCII only
+    lines: Vec<(usize, bool)>,
+    output: String,
+    line: usize,
+    column: usize,
+    mappings: Vec<Mapping>,
+}
+
+impl<'a> SourceMapWriter<'a> {
+    fn new(input: &'a str) -> Self {
+        let mut lines = vec![];
+        let mut start = 0;
+        for line in input.split_inclusive('\n') {
+            lines.push((start, line.is_ascii()));
+            start += line.len();
+        }
+        if lines.is_empty() {
+            lines.push((0, true));
+        }
+        SourceMapWriter {
+            input,
+            lines,
+            output: String::new(),
+            line: 0,
+            column: 0,
+            mappings: vec![],
+        }
+    }
+}
+
+impl Write for SourceMapWriter<'_> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.output.push_str(s);
+        match s.rfind('\n') {
+            Some(i) => {
+                self.line += s.matches('\n').count();
+                self.column = s[i + 1..].encode_utf16().count();
+            }
+            None if s.is_ascii() => self.column += s.len(),
+            None => self.column += s.encode_utf16().count(),
+        }
+        Ok(())
+    }
+}
+
+impl CssWriter for SourceMapWriter<'_> {
+    fn mark(&mut self, location: Location) {
+        let Some(offset) = location.0 else {
+            return;
+        };
+        let offset = offset.min(self.input.len());
+        let line = self.lines.partition_point(|&(start, _)| start <= offset) - 1;
+        let (start, ascii) = self.lines[line];
+        let column = match ascii {
+            true => offset - start,
+            false => self.input[start..offset].encode_utf16().count(),
+        };
+        if let Some(last) = self.mappings.last() {
+            if (last.generated_line, last.generated_column) == (self.line, self.column) {
+                return;
+            }
+        }
+        self.mappings.push(Mapping {
+            generated_line: self.line,
+            generated_column: self.column,
+            source: 0,
+            original_line: line,
+            original_column: column,
+        });
+    }
+}
+
+fn encode_vlq(encoded: &mut String, value: usize, previous: usize) {
+    let delta = value as i64 - previous as i64;
+    let mut vlq = if delta < 0 {
+        ((-delta) << 1) | 1
+    } else {
+        delta << 1
+    };
+    loop {
+        let mut digit = vlq & 0b11111;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0b100000;
+        }
+        encoded.push(BASE64[digit as usize] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+/// Mappings decoded from base64 VLQ segments, segments without the original position are skipped
+fn decode_mappings(encoded: &str) -> Option<Vec<Mapping>> {
+    let mut mappings = vec![];
+    let mut state = [0i64; 4];
+    for (generated_line, line) in encoded.split(';').enumerate() {
+        state[0] = 0;
+        for segment in line.split(',').filter(|s| !s.is_empty()) {
+            let fields = decode_vlq(segment)?;
+            if !matches!(fields.len(), 1 | 4 | 5) {
+                return None;
+            }
+            for (delta, value) in fields.iter().zip(state.iter_mut()) {
+                *value = value.checked_add(*delta)?;
+            }
+            if state.iter().any(|value| *value < 0) {
+                return None;
+            }
+            if fields.len() > 1 {
+                mappings.push(Mapping {
+                    generated_line,
+                    generated_column: state[0] as usize,
+                    source: state[1] as usize,
+                    original_line: state[2] as usize,
+                    original_column: state[3] as usize,
+                });
+            }
+        }
+    }
+    mappings.sort_by_key(|m| (m.generated_line, m.generated_column));
+    Some(mappings)
+}
+
+fn decode_vlq(segment: &str) -> Option<Vec<i64>> {
+    let mut values = vec![];
+    let (mut value, mut shift) = (0i64, 0);
+    for c in segment.bytes() {
+        let digit = BASE64.iter().position(|&b| b == c)? as i64;
+        let bits = (digit & 0b11111)
+            .checked_shl(shift)
+            .filter(|bits| bits >> shift == digit & 0b11111)?;
+        value = value.checked_add(bits)?;
+        if digit & 0b100000 == 0 {
+            values.push(match value & 1 {
+                1 => -(value >> 1),
+                _ => value >> 1,
+            });
+            (value, shift) = (0, 0);
+        } else {
+            shift += 5;
+            if shift > 60 {
+                return None;
+            }
+        }
+    }
+    (shift == 0).then_some(values)
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - i * 8));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[(n >> (18 - i * 6) & 0b111111) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn push_json_string(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+#[cfg(test)]
+mod test {
+    use crate::optimizations::{Level, Minifier};
+    use crate::parse_tolerant;
+    use crate::source_map::{
+        decode_mappings, encode_base64, write_with_source_map, Mapping, ParseSourceMapError,
+        SourceMap, SourceMapOptions, SourceMapUrl,
+    };
+
+    fn mapping(generated_column: usize, original_line: usize, original_column: usize) -> Mapping {
+        Mapping {
+            generated_line: 0,
+            generated_column,
+            source: 0,
+            original_line,
+            original_column,
+        }
+    }
+
+    #[test]
+    fn test_encode_mappings() {
+        let source_map = SourceMap {
+            mappings: vec![mapping(0, 0, 0), mapping(2, 1, 4), mapping(40, 0, 1)],
+            ..Default::default()
+        };
+        assert_eq!(source_map.encode_mappings(), "AAAA,EACI,sCADH");
+    }
+
+    #[test]
+    fn test_decode_mappings() {
+        let source_map = SourceMap {
+            mappings: vec![
+                mapping(0, 0, 0),
+                mapping(2, 1, 4),
+                Mapping {
+                    generated_line: 2,
+                    generated_column: 5,
+                    source: 1,
+                    original_line: 0,
+                    original_column: 1,
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            decode_mappings(&source_map.encode_mappings()),
+            Some(source_map.mappings)
+        );
+        assert_eq!(
+            decode_mappings("AAAA,C;;E,GAAAA"),
+            decode_mappings("AAAA;;KAAA")
+        );
+        assert_eq!(decode_mappings("AA"), None);
+        assert_eq!(decode_mappings("AAA!"), None);
+        assert_eq!(decode_mappings("A////////////PAA"), None);
+        assert!(decode_mappings(&["AggggggggggggEAA"; 3].join(",")).is_some());
+        assert_eq!(decode_mappings(&["AggggggggggggEAA"; 4].join(",")), None);
+    }
+
+    #[test]
+    fn test_input_source_map() {
+        // `.a { .b { color: red } }` compiled by a preprocessor into `.a .b {\n  color: red;\n}`
+        let input_map = SourceMap::from_json(
+            r#"{
+                "version": 3,
+                "sourceRoot": "styles",
+                "sources": ["main.scss"],
+                "sourcesContent": [".a { .b { color: red } }"],
+                "names": [],
+                "mappings": "AAAK;EAAK"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(input_map.sources, vec!["styles/main.scss"]);
+        assert_eq!(
+            input_map.mappings,
+            vec![
+                mapping(0, 0, 5),
+                Mapping {
+                    generated_line: 1,
+                    ..mapping(2, 0, 10)
+                }
+            ]
+        );
+
+        let (css, source_map) = Minifier::default()
+            .minify_with_source_map(
+                ".a .b {\n  color: red;\n}",
+                Level::One,
+                &SourceMapOptions {
+                    source: "main.css".into(),
+                    input_map: Some(input_map),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(css, ".a .b{color:red}");
+        assert_eq!(source_map.sources, vec!["styles/main.scss"]);
+        assert_eq!(
+            source_map.sources_content,
+            vec![Some(".a { .b { color: red } }".into())]
+        );
+        assert_eq!(
+            source_map.mappings,
+            vec![mapping(0, 0, 5), mapping(6, 0, 10)]
+        );
+    }
+
+    #[test]
+    fn test_invalid_source_map() {
+        assert_eq!(
+            SourceMap::from_json("{\"version\": 3"),
+            Err(ParseSourceMapError::InvalidJson)
+        );
+        assert_eq!(
+            SourceMap::from_json(r#"{"version": 2, "sources": [], "mappings": ""}"#),
+            Err(ParseSourceMapError::UnsupportedVersion)
+        );
+        assert_eq!(
+            SourceMap::from_json(r#"{"version": 3, "sources": [], "mappings": "AAAA"}"#),
+            Err(ParseSourceMapError::InvalidMappings)
+        );
+    }
+
+    #[test]
+    fn test_encode_base64() {
+        assert_eq!(encode_base64(b"css"), "Y3Nz");
+        assert_eq!(encode_base64(b"a{}"), "YXt9");
+        assert_eq!(encode_base64(b"a{b}"), "YXtifQ==");
+        assert_eq!(encode_base64(b"a{b:"), "YXtiOg==");
+        assert_eq!(encode_base64(b"a{b:c"), "YXtiOmM=");
+    }
+
+    #[test]
+    fn test_source_map() {
+        let input = "a {\n  color: red;\n  margin: 0px;\n}\n\n.ы, b {\n  color: blue\n}\n";
+        let (css, source_map) = Minifier::default()
+            .minify_with_source_map(
+                input,
+                Level::One,
+                &SourceMapOptions {
+                    source: "main.css".into(),
+                    file: Some("main.min.css".into()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(css, Minifier::default().minify(input, Level::One).unwrap());
+        assert_eq!(css, "a{color:red;margin:0}.ы,b{color:blue}");
+        assert_eq!(
+            source_map.mappings,
+            vec![
+                mapping(0, 0, 0),
+                mapping(2, 1, 2),
+                mapping(12, 2, 2),
+                mapping(21, 5, 0),
+                mapping(26, 6, 2)
+            ]
+        );
+        assert_eq!(
+            source_map.to_json(),
+            r#"{"version":3,"file":"main.min.css","sources":["main.css"],"names":[],"mappings":"AAAA,EACE,UACA,SAGF,KACE"}"#
+        );
+    }
+
+    #[test]
+    fn test_source_map_merged() {
+        let input = "a { color: red }\n@media print { a { color: black } }\nb { margin: 0 }\na { padding: 0 }\n@media print { b { color: white } }";
+        let (css, source_map) = Minifier::default()
+            .minify_with_source_map(input, Level::Three, &Default::default())
+            .unwrap();
+        assert_eq!(
+            css,
+            Minifier::default().minify(input, Level::Three).unwrap()
+        );
+        assert_eq!(
+            css,
+            "a{color:red;padding:0}@media print{a{color:#000}b{color:#fff}}b{margin:0}"
+        );
+        let original = |column: usize| {
+            source_map
+                .mappings
+                .iter()
+                .find(|m| m.generated_column == column)
+                .map(|m| (m.original_line, m.original_column))
+        };
+        assert_eq!(original(12), Some((3, 4)));
+        assert_eq!(original(35), Some((1, 15)));
+        assert_eq!(original(37), Some((1, 19)));
+        assert_eq!(original(48), Some((4, 15)));
+        assert_eq!(original(50), Some((4, 19)));
+        assert_eq!(original(62), Some((2, 0)));
+        assert_eq!(original(64), Some((2, 4)));
+    }
+
+    #[test]
+    fn test_source_map_tolerant() {
+        let input = "a { color: red;; margin: } b { color: blue }";
+        let (stylesheet, _) = parse_tolerant(input);
+        let (css, source_map) = write_with_source_map(&stylesheet, input, &Default::default());
+        assert_eq!(css, "a{color:red}b{color:blue}");
+        assert_eq!(
+            source_map.mappings,
+            vec![
+                mapping(0, 0, 0),
+                mapping(2, 0, 4),
+                mapping(12, 0, 27),
+                mapping(14, 0, 31)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_source_map_url() {
+        let input = "a { color: red }";
+        let minify = |url| {
+            Minifier::default()
+                .minify_with_source_map(
+                    input,
+                    Level::One,
+                    &SourceMapOptions {
+                        source: "a.css".into(),
+                        include_content: true,
+                        url,
+                        ..Default::default()
+                    },
+                )
+                .unwrap()
+        };
+        let (css, source_map) = minify(SourceMapUrl::External("a.min.css.map".into()));
+        assert_eq!(css, "a{color:red}/*# sourceMappingURL=a.min.css.map */");
+        assert_eq!(
+            source_map.to_json(),
+            r#"{"version":3,"sources":["a.css"],"sourcesContent":["a { color: red }"],"names":[],"mappings":"AAAA,EAAI"}"#
+        );
+        let (css, source_map) = minify(SourceMapUrl::Inline);
+        assert_eq!(
+            css,
+            format!(
+                "a{{color:red}}/*# sourceMappingURL={} */",
+                source_map.to_data_url()
+            )
+        );
+    }
+}
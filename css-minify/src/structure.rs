@@ -1,294 +1,919 @@
+use crate::parsers::value::parse_component_values;
 use derive_more::{Deref, DerefMut, Display as DeriveDisplay, From, Into};
 use indexmap::map::IndexMap;
+use std::borrow::Cow;
 use std::fmt::Display;
 use std::fmt::Formatter;
 
+/// Css rule, `entities` are the nested rules. Block without selectors holds
+/// declarations placed directly into a nested at-rule (`a{@media print{color:red}}`)
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Block<'a> {
+    pub selectors: Selectors<'a>,
+    pub parameters: Parameters<'a>,
+    pub entities: CssEntities<'a>,
+    pub location: Location,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, From, Into)]
+pub struct Media<'a> {
+    pub screen: Name<'a>,
+    pub entities: CssEntities<'a>,
+}
+
 #[derive(Clone, Eq, PartialEq, Debug, From, Into)]
-pub struct Block {
-    pub selectors: Selectors,
-    pub parameters: Parameters,
+pub struct Container<'a> {
+    pub name: Option<Name<'a>>,
+    pub condition: Name<'a>,
+    pub entities: CssEntities<'a>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, From, Into)]
-pub struct Media {
-    pub screen: Name,
-    pub entities: CssEntities,
+pub struct Page<'a> {
+    pub selectors: Option<Name<'a>>,
+    pub parameters: Parameters<'a>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, From, Into)]
-pub struct Page {
-    pub selectors: Option<Name>,
-    pub parameters: Parameters,
+pub struct Supports<'a> {
+    pub conditions: Name<'a>,
+    pub entities: CssEntities<'a>,
 }
 
+/// `@layer` statement when there are no entities, otherwise the layer block
 #[derive(Clone, Eq, PartialEq, Debug, From, Into)]
-pub struct Supports {
-    pub conditions: Name,
-    pub entities: CssEntities,
+pub struct Layer<'a> {
+    pub names: Vec<Name<'a>>,
+    pub entities: Option<CssEntities<'a>>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, From, Into)]
-pub struct Keyframes {
+pub struct Keyframes<'a> {
     pub webkit_prefix: bool,
-    pub name: Name,
-    pub blocks: KeyframeBlocks,
+    pub name: Name<'a>,
+    pub blocks: KeyframeBlocks<'a>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, From, Into)]
-pub struct KeyframeBlock {
-    pub name: Name,
-    pub parameters: Parameters,
+pub struct KeyframeBlock<'a> {
+    pub name: Name<'a>,
+    pub parameters: Parameters<'a>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, From, Into)]
-pub struct FontFace {
-    pub parameters: Parameters,
+pub struct FontFace<'a> {
+    pub parameters: Parameters<'a>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, From, Into)]
-pub struct Viewport {
-    pub parameters: Parameters,
+pub struct Viewport<'a> {
+    pub parameters: Parameters<'a>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, From, Into)]
-pub struct MsViewport {
-    pub parameters: Parameters,
+pub struct MsViewport<'a> {
+    pub parameters: Parameters<'a>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, From, Into)]
-pub struct NamespaceAt {
-    prefix: Option<Value>,
-    url: Value,
+pub struct NamespaceAt<'a> {
+    pub prefix: Option<Value<'a>>,
+    pub url: Value<'a>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, From, Into)]
-pub struct ImportAt {
-    url: Value,
-    media_queries: Option<Value>,
+pub struct ImportAt<'a> {
+    pub url: Value<'a>,
+    pub media_queries: Option<Value<'a>>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, From, Into)]
-pub struct CharsetAt {
-    charset: Value,
+pub struct CharsetAt<'a> {
+    pub charset: Value<'a>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, From)]
-pub enum At {
-    Namespace(NamespaceAt),
-    Import(ImportAt),
-    Charset(CharsetAt),
+pub enum At<'a> {
+    Namespace(NamespaceAt<'a>),
+    Import(ImportAt<'a>),
+    Charset(CharsetAt<'a>),
 }
 
-#[derive(Clone, Eq, PartialEq, Debug, From, DeriveDisplay)]
-pub enum CssEntity {
-    Block(Block),
-    Media(Media),
-    Page(Page),
-    Supports(Supports),
-    FontFace(FontFace),
-    Viewport(Viewport),
-    MsViewport(MsViewport),
-    Keyframes(Keyframes),
-    At(At),
+#[derive(Clone, Eq, PartialEq, Debug, From)]
+pub enum CssEntity<'a> {
+    Block(Block<'a>),
+    Media(Media<'a>),
+    Container(Container<'a>),
+    Page(Page<'a>),
+    Supports(Supports<'a>),
+    Layer(Layer<'a>),
+    FontFace(FontFace<'a>),
+    Viewport(Viewport<'a>),
+    MsViewport(MsViewport<'a>),
+    Keyframes(Keyframes<'a>),
+    At(At<'a>),
 }
 
-#[derive(Clone, Eq, PartialEq, Default, Debug, Deref, DerefMut, From, Into)]
-pub struct Selectors(pub(crate) Vec<SelectorWithPseudoClasses>);
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Default, Debug, Deref, DerefMut, From, Into)]
+pub struct Selectors<'a>(pub Vec<ComplexSelector<'a>>);
 
+/// Declarations of a block in source order, duplicated names are kept as fallbacks
 #[derive(Clone, Eq, PartialEq, Default, Debug, Deref, DerefMut, From, Into)]
-pub struct Parameters(pub(crate) IndexMap<Name, Value>);
+pub struct Parameters<'a>(pub Vec<Parameter<'a>>);
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Parameter<'a> {
+    pub name: Name<'a>,
+    pub value: Value<'a>,
+    pub location: Location,
+}
+
+impl<'a> From<(Name<'a>, Value<'a>)> for Parameter<'a> {
+    fn from((name, value): (Name<'a>, Value<'a>)) -> Self {
+        Parameter {
+            name,
+            value,
+            location: Location::default(),
+        }
+    }
+}
+
+/// Byte offset of the rule or declaration in the parsed input, `None` for created ones.
+/// Locations are never compared, so equal trees parsed from different inputs are equal
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Location(pub Option<usize>);
+
+impl PartialEq for Location {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Location {}
 
 #[derive(Clone, Eq, PartialEq, Default, Debug, Deref, DerefMut, From, Into)]
-pub struct Blocks(pub(crate) Vec<Block>);
+pub struct Blocks<'a>(pub Vec<Block<'a>>);
 
 #[derive(Clone, Eq, PartialEq, Default, Debug, Deref, DerefMut, From, Into)]
-pub struct KeyframeBlocks(pub(crate) Vec<KeyframeBlock>);
+pub struct KeyframeBlocks<'a>(pub Vec<KeyframeBlock<'a>>);
 
 #[derive(Clone, Eq, PartialEq, Default, Debug, Deref, DerefMut, From, Into)]
-pub struct CssEntities(pub(crate) Vec<CssEntity>);
+pub struct CssEntities<'a>(pub Vec<CssEntity<'a>>);
 
-pub type Name = String;
-pub type Value = String;
+/// Parsed stylesheet, the root of the css tree
+pub type Stylesheet<'a> = CssEntities<'a>;
 
-pub type Id = String;
-pub type Class = String;
-pub type Tag = String;
+pub type Name<'a> = Cow<'a, str>;
+pub type Value<'a> = Cow<'a, str>;
 
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
-pub struct SelectorWithPseudoClasses(pub Option<Selector>, pub Vec<PseudoClass>);
-impl Display for SelectorWithPseudoClasses {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        if let Some(s) = &self.0 {
-            write!(f, "{}", s)?
+pub type Id<'a> = Cow<'a, str>;
+pub type Class<'a> = Cow<'a, str>;
+pub type Tag<'a> = Cow<'a, str>;
+
+impl<'a> Parameters<'a> {
+    /// Returns the value of the last declaration with the given name
+    pub fn get(&self, name: &str) -> Option<&Value<'a>> {
+        self.0
+            .iter()
+            .rev()
+            .find(|p| p.name == name)
+            .map(|p| &p.value)
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.0.iter().any(|p| p.name == name)
+    }
+
+    /// Returns how many times the declaration with the given name is repeated
+    pub fn count(&self, name: &str) -> usize {
+        self.0.iter().filter(|p| p.name == name).count()
+    }
+
+    /// Replaces every declaration with one of the given names by a single one
+    /// placed at the position of the first of them
+    pub fn replace(&mut self, names: &[&str], name: Name<'a>, value: Value<'a>) {
+        let index = self
+            .0
+            .iter()
+            .position(|p| names.contains(&&*p.name))
+            .unwrap_or(self.0.len());
+        let location = self.0.get(index).map(|p| p.location).unwrap_or_default();
+        self.0.insert(
+            index,
+            Parameter {
+                name,
+                value,
+                location,
+            },
+        );
+        self.0.retain(|p| !names.contains(&&*p.name));
+    }
+
+    /// Removes every declaration with the given name
+    pub fn remove(&mut self, name: &str) {
+        self.0.retain(|p| p.name != name)
+    }
+}
+
+/// Conversion of the tree borrowing strings from the parsed input into the tree owning them,
+/// it may outlive the input then
+pub trait IntoOwned {
+    type Owned;
+
+    fn into_owned(self) -> Self::Owned;
+}
+
+impl IntoOwned for Cow<'_, str> {
+    type Owned = Cow<'static, str>;
+
+    fn into_owned(self) -> Cow<'static, str> {
+        Cow::Owned(Cow::into_owned(self))
+    }
+}
+
+impl<T: IntoOwned> IntoOwned for Option<T> {
+    type Owned = Option<T::Owned>;
+
+    fn into_owned(self) -> Self::Owned {
+        self.map(IntoOwned::into_owned)
+    }
+}
+
+impl<T: IntoOwned> IntoOwned for Vec<T> {
+    type Owned = Vec<T::Owned>;
+
+    fn into_owned(self) -> Self::Owned {
+        self.into_iter().map(IntoOwned::into_owned).collect()
+    }
+}
+
+impl<A: IntoOwned, B: IntoOwned> IntoOwned for (A, B) {
+    type Owned = (A::Owned, B::Owned);
+
+    fn into_owned(self) -> Self::Owned {
+        (IntoOwned::into_owned(self.0), IntoOwned::into_owned(self.1))
+    }
+}
+
+macro_rules! into_owned {
+    ($($t:ty),* $(,)?) => {
+        $(impl IntoOwned for $t {
+            type Owned = $t;
+
+            fn into_owned(self) -> Self::Owned {
+                self
+            }
+        })*
+    };
+    ($($t:ident { $($field:tt),* }),* $(,)?) => {
+        $(impl IntoOwned for $t<'_> {
+            type Owned = $t<'static>;
+
+            fn into_owned(self) -> Self::Owned {
+                $t { $($field: IntoOwned::into_owned(self.$field)),* }
+            }
+        })*
+    };
+}
+
+into_owned!(bool, char, Location, Combinator, AttributeOperator);
+
+into_owned!(
+    Block { selectors, parameters, entities, location },
+    Media { screen, entities },
+    Container { name, condition, entities },
+    Page { selectors, parameters },
+    Supports { conditions, entities },
+    Layer { names, entities },
+    Keyframes { webkit_prefix, name, blocks },
+    KeyframeBlock { name, parameters },
+    FontFace { parameters },
+    Viewport { parameters },
+    MsViewport { parameters },
+    NamespaceAt { prefix, url },
+    ImportAt { url, media_queries },
+    CharsetAt { charset },
+    Parameter { name, value, location },
+    Attribute { name, matcher },
+    AttributeMatcher { operator, value, quote, flag },
+    PseudoClass { name, argument },
+    PseudoElement { name, argument, legacy },
+    Selectors { 0 },
+    Parameters { 0 },
+    Blocks { 0 },
+    KeyframeBlocks { 0 },
+    CssEntities { 0 },
+    ComplexSelector { 0 },
+    CompoundSelector { 0 },
+);
+
+impl IntoOwned for At<'_> {
+    type Owned = At<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        match self {
+            At::Namespace(namespace) => At::Namespace(namespace.into_owned()),
+            At::Import(import) => At::Import(import.into_owned()),
+            At::Charset(charset) => At::Charset(charset.into_owned()),
         }
-        for pc in self.1.iter() {
-            write!(f, "{}", pc)?
+    }
+}
+
+impl IntoOwned for CssEntity<'_> {
+    type Owned = CssEntity<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        match self {
+            CssEntity::Block(block) => CssEntity::Block(block.into_owned()),
+            CssEntity::Media(media) => CssEntity::Media(media.into_owned()),
+            CssEntity::Container(container) => CssEntity::Container(container.into_owned()),
+            CssEntity::Page(page) => CssEntity::Page(page.into_owned()),
+            CssEntity::Supports(supports) => CssEntity::Supports(supports.into_owned()),
+            CssEntity::Layer(layer) => CssEntity::Layer(layer.into_owned()),
+            CssEntity::FontFace(font_face) => CssEntity::FontFace(font_face.into_owned()),
+            CssEntity::Viewport(viewport) => CssEntity::Viewport(viewport.into_owned()),
+            CssEntity::MsViewport(viewport) => CssEntity::MsViewport(viewport.into_owned()),
+            CssEntity::Keyframes(keyframes) => CssEntity::Keyframes(keyframes.into_owned()),
+            CssEntity::At(at) => CssEntity::At(at.into_owned()),
         }
+    }
+}
 
-        Ok(())
+impl IntoOwned for Selector<'_> {
+    type Owned = Selector<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        match self {
+            Selector::Tag(tag) => Selector::Tag(IntoOwned::into_owned(tag)),
+            Selector::Id(id) => Selector::Id(IntoOwned::into_owned(id)),
+            Selector::Class(class) => Selector::Class(IntoOwned::into_owned(class)),
+            Selector::Attribute(attribute) => Selector::Attribute(attribute.into_owned()),
+            Selector::PseudoClass(pc) => Selector::PseudoClass(pc.into_owned()),
+            Selector::PseudoElement(pe) => Selector::PseudoElement(pe.into_owned()),
+            Selector::Nesting => Selector::Nesting,
+        }
     }
 }
 
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
-pub struct PseudoClass {
-    pub name: String,
-    pub params: Option<String>,
-    pub next: Option<String>,
-    pub prefix: String,
+impl IntoOwned for PseudoArgument<'_> {
+    type Owned = PseudoArgument<'static>;
+
+    fn into_owned(self) -> Self::Owned {
+        match self {
+            PseudoArgument::Selectors(selectors) => {
+                PseudoArgument::Selectors(selectors.into_owned())
+            }
+            PseudoArgument::Raw(raw) => PseudoArgument::Raw(IntoOwned::into_owned(raw)),
+        }
+    }
+}
+
+impl<'a> CssEntities<'a> {
+    /// Maps every location of the tree, parsers record them as the remaining length of the input
+    pub(crate) fn relocate(&mut self, offset: &impl Fn(usize) -> usize) {
+        self.0.iter_mut().for_each(|entity| match entity {
+            CssEntity::Block(block) => {
+                block.location.relocate(offset);
+                block.parameters.relocate(offset);
+                block.entities.relocate(offset);
+            }
+            CssEntity::Media(Media { entities, .. })
+            | CssEntity::Container(Container { entities, .. })
+            | CssEntity::Supports(Supports { entities, .. })
+            | CssEntity::Layer(Layer {
+                entities: Some(entities),
+                ..
+            }) => entities.relocate(offset),
+            CssEntity::Page(Page { parameters, .. })
+            | CssEntity::FontFace(FontFace { parameters })
+            | CssEntity::Viewport(Viewport { parameters })
+            | CssEntity::MsViewport(MsViewport { parameters }) => parameters.relocate(offset),
+            CssEntity::Keyframes(keyframes) => keyframes
+                .blocks
+                .0
+                .iter_mut()
+                .for_each(|block| block.parameters.relocate(offset)),
+            CssEntity::Layer(_) | CssEntity::At(_) => {}
+        })
+    }
 }
 
-impl Display for PseudoClass {
+impl<'a> Parameters<'a> {
+    fn relocate(&mut self, offset: &impl Fn(usize) -> usize) {
+        self.0
+            .iter_mut()
+            .for_each(|parameter| parameter.location.relocate(offset))
+    }
+}
+
+impl Location {
+    fn relocate(&mut self, offset: &impl Fn(usize) -> usize) {
+        self.0 = self.0.map(offset);
+    }
+}
+
+impl<'a> From<IndexMap<Name<'a>, Value<'a>>> for Parameters<'a> {
+    fn from(map: IndexMap<Name<'a>, Value<'a>>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl<'a> FromIterator<(Name<'a>, Value<'a>)> for Parameters<'a> {
+    fn from_iter<T: IntoIterator<Item = (Name<'a>, Value<'a>)>>(iter: T) -> Self {
+        Parameters(iter.into_iter().map(Parameter::from).collect())
+    }
+}
+
+/// Compound selectors joined with combinators: `div > p.foo`.
+/// The first compound has a combinator only in relative selectors (`> p` nested into a rule)
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Default, Debug, Deref, DerefMut, From, Into)]
+pub struct ComplexSelector<'a>(pub Vec<(Option<Combinator>, CompoundSelector<'a>)>);
+
+impl Display for ComplexSelector<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}{}", self.prefix, self.name)?;
-        if let Some(params) = &self.params {
-            write!(f, "({})", params)?;
+        for (i, (combinator, compound)) in self.0.iter().enumerate() {
+            match combinator {
+                Some(combinator) if i == 0 => write!(f, "{}", combinator.to_string().trim_start())?,
+                Some(combinator) => write!(f, "{}", combinator)?,
+                None => {}
+            }
+            write!(f, "{}", compound)?
         }
-        if let Some(next) = &self.next {
-            write!(f, " {}", next)?;
+        Ok(())
+    }
+}
+
+impl<'a> From<CompoundSelector<'a>> for ComplexSelector<'a> {
+    fn from(compound: CompoundSelector<'a>) -> Self {
+        ComplexSelector(vec![(None, compound)])
+    }
+}
+
+impl<'a> From<Selector<'a>> for ComplexSelector<'a> {
+    fn from(selector: Selector<'a>) -> Self {
+        CompoundSelector(vec![selector]).into()
+    }
+}
+
+/// Combinator between compound selectors, `spaced` keeps whitespace around `>`, `+` and `~`
+#[derive(Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Debug)]
+pub struct Combinator {
+    pub kind: CombinatorKind,
+    pub spaced: bool,
+}
+
+#[derive(Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Debug)]
+pub enum CombinatorKind {
+    /// `a b`
+    Descendant,
+    /// `a > b`
+    Child,
+    /// `a + b`
+    NextSibling,
+    /// `a ~ b`
+    SubsequentSibling,
+}
+
+impl Display for Combinator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self.kind {
+            CombinatorKind::Descendant => return write!(f, " "),
+            CombinatorKind::Child => ">",
+            CombinatorKind::NextSibling => "+",
+            CombinatorKind::SubsequentSibling => "~",
+        };
+        if self.spaced {
+            write!(f, " {} ", symbol)
+        } else {
+            write!(f, "{}", symbol)
         }
+    }
+}
 
+/// Simple selectors without combinators between them: `p.foo[data-x]:hover`
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Default, Debug, Deref, DerefMut, From, Into)]
+pub struct CompoundSelector<'a>(pub Vec<Selector<'a>>);
+
+impl Display for CompoundSelector<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for selector in self.0.iter() {
+            write!(f, "{}", selector)?
+        }
         Ok(())
     }
 }
 
 #[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
-pub enum Selector {
-    Id(Id),
-    Class(Class),
-    Tag(Tag),
+pub enum Selector<'a> {
+    /// Type or universal selector, with the namespace prefix if any: `p`, `*`, `svg|a`
+    Tag(Tag<'a>),
+    Id(Id<'a>),
+    Class(Class<'a>),
+    Attribute(Attribute<'a>),
+    PseudoClass(PseudoClass<'a>),
+    PseudoElement(PseudoElement<'a>),
+    /// `&` of the nested rule
+    Nesting,
 }
 
-impl Display for Selector {
+impl Display for Selector<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            Selector::Id(id) => {
-                write!(f, "#{}", id)
-            }
-            Selector::Class(class) => {
-                write!(f, ".{}", class)
+            Selector::Tag(tag) => write!(f, "{}", tag),
+            Selector::Id(id) => write!(f, "#{}", id),
+            Selector::Class(class) => write!(f, ".{}", class),
+            Selector::Attribute(attribute) => write!(f, "{}", attribute),
+            Selector::PseudoClass(pc) => write!(f, "{}", pc),
+            Selector::PseudoElement(pe) => write!(f, "{}", pe),
+            Selector::Nesting => write!(f, "&"),
+        }
+    }
+}
+
+/// `[name]` or `[name operator value flag]`
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
+pub struct Attribute<'a> {
+    pub name: Name<'a>,
+    pub matcher: Option<AttributeMatcher<'a>>,
+}
+
+/// Value is stored without quotes, `quote` keeps the quote character of the source
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
+pub struct AttributeMatcher<'a> {
+    pub operator: AttributeOperator,
+    pub value: Value<'a>,
+    pub quote: Option<char>,
+    pub flag: Option<char>,
+}
+
+#[derive(Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Debug, DeriveDisplay)]
+pub enum AttributeOperator {
+    #[display("=")]
+    Equal,
+    #[display("~=")]
+    Includes,
+    #[display("|=")]
+    DashMatch,
+    #[display("^=")]
+    Prefix,
+    #[display("$=")]
+    Suffix,
+    #[display("*=")]
+    Substring,
+}
+
+impl Display for Attribute<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}", self.name)?;
+        if let Some(matcher) = &self.matcher {
+            write!(f, "{}", matcher.operator)?;
+            match matcher.quote {
+                Some(quote) => write!(f, "{}{}{}", quote, matcher.value, quote)?,
+                None => write!(f, "{}", matcher.value)?,
             }
-            Selector::Tag(tag) => {
-                write!(f, "{}", tag)
+            if let Some(flag) = matcher.flag {
+                write!(f, " {}", flag)?;
             }
         }
+        write!(f, "]")
     }
 }
 
-impl Display for Selectors {
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
+pub struct PseudoClass<'a> {
+    pub name: Name<'a>,
+    pub argument: Option<PseudoArgument<'a>>,
+}
+
+/// `::name`, `legacy` pseudo elements are written with a single colon (`:before`)
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
+pub struct PseudoElement<'a> {
+    pub name: Name<'a>,
+    pub argument: Option<PseudoArgument<'a>>,
+    pub legacy: bool,
+}
+
+/// Argument of the functional pseudo class or element,
+/// selector lists are parsed (`:not(a, .b)`), others are kept as is (`:nth-child(2n+1)`)
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Debug, From)]
+pub enum PseudoArgument<'a> {
+    Selectors(Selectors<'a>),
+    Raw(Value<'a>),
+}
+
+impl Display for PseudoArgument<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let mut selectors = self
-            .0
-            .iter()
-            .map(|selector| format!("{}", selector))
-            .collect::<Vec<String>>();
-        selectors.sort();
-        write!(f, "{}", selectors.join(","))
+        match self {
+            PseudoArgument::Selectors(selectors) => write!(f, "({})", selectors),
+            PseudoArgument::Raw(raw) => write!(f, "({})", raw),
+        }
     }
 }
 
-impl Display for Parameters {
+impl Display for PseudoClass<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let parameters = self
-            .0
-            .iter()
-            .map(|(name, value)| format!("{}:{}", name, value))
-            .collect::<Vec<String>>();
-        write!(f, "{}", parameters.join(";"))
+        write!(f, ":{}", self.name)?;
+        if let Some(argument) = &self.argument {
+            write!(f, "{}", argument)?;
+        }
+        Ok(())
     }
 }
 
-impl Display for Block {
+impl Display for PseudoElement<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}{{{}}}", self.selectors, self.parameters)
+        if self.legacy {
+            write!(f, ":{}", self.name)?;
+        } else {
+            write!(f, "::{}", self.name)?;
+        }
+        if let Some(argument) = &self.argument {
+            write!(f, "{}", argument)?;
+        }
+        Ok(())
     }
 }
 
-impl Display for KeyframeBlock {
+impl Display for Selectors<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}{{{}}}", self.name, self.parameters)
+        for (i, selector) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", selector)?;
+        }
+        Ok(())
+    }
+}
+
+/// Value of a declaration split into css component values
+#[derive(Clone, Eq, PartialEq, Default, Debug, Deref, DerefMut, From, Into)]
+pub struct ComponentValues(pub Vec<ComponentValue>);
+
+/// Token of a declaration value, numbers keep their source text
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum ComponentValue {
+    Ident(String),
+    /// `5`, `-0.5`, `1e3`
+    Number(String),
+    /// Number with its unit: `5px`
+    Dimension(String, String),
+    /// Number of the percentage without `%`
+    Percentage(String),
+    /// String with its quotes and escapes as written
+    String(String),
+    /// Content of the unquoted `url()`
+    Url(String),
+    /// Name of the hash without `#`: colors and ids
+    Hash(String),
+    /// Function with its arguments: `calc(1px + 2%)`
+    Function(String, ComponentValues),
+    /// Content of `()` or `[]` with the opening bracket
+    Block(char, ComponentValues),
+    Comma,
+    /// Whitespaces and comments
+    Whitespace,
+    /// Any other character: `/`, `+`, `!` and etc
+    Delim(char),
+}
+
+impl ComponentValues {
+    pub fn parse(value: &str) -> Self {
+        parse_component_values(value)
     }
 }
 
-impl Display for Media {
+impl Display for ComponentValues {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "@media {}{{{}}}", self.screen, self.entities)
+        self.0.iter().try_for_each(|value| write!(f, "{}", value))
     }
 }
 
-impl Display for Page {
+impl Display for ComponentValue {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "@page")?;
-        if let Some(selectors) = &self.selectors {
-            write!(f, " {}", selectors)?
+        match self {
+            ComponentValue::Ident(value)
+            | ComponentValue::Number(value)
+            | ComponentValue::String(value) => write!(f, "{}", value),
+            ComponentValue::Dimension(number, unit) => write!(f, "{}{}", number, unit),
+            ComponentValue::Percentage(number) => write!(f, "{}%", number),
+            ComponentValue::Url(url) => write!(f, "url({})", url),
+            ComponentValue::Hash(hash) => write!(f, "#{}", hash),
+            ComponentValue::Function(name, arguments) => write!(f, "{}({})", name, arguments),
+            ComponentValue::Block(open, content) => {
+                let close = if *open == '[' { ']' } else { ')' };
+                write!(f, "{}{}{}", open, content, close)
+            }
+            ComponentValue::Comma => write!(f, ","),
+            ComponentValue::Whitespace => write!(f, " "),
+            ComponentValue::Delim(c) => write!(f, "{}", c),
+        }
+    }
+}
+
+/// Destination of the serialized css, `mark` is called at the start
+/// of every rule and declaration with its location in the parsed input
+pub trait CssWriter: std::fmt::Write {
+    fn mark(&mut self, _location: Location) {}
+}
+
+impl CssWriter for Formatter<'_> {}
+
+/// Serialization of the css tree, `Display` writes the same output
+pub trait WriteCss {
+    fn write_css<W: CssWriter>(&self, w: &mut W) -> std::fmt::Result;
+}
+
+macro_rules! display_css {
+    ($($t:ty),*) => {
+        $(impl Display for $t {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                self.write_css(f)
+            }
+        })*
+    };
+}
+
+display_css!(
+    Parameter<'_>,
+    Parameters<'_>,
+    Block<'_>,
+    KeyframeBlock<'_>,
+    Media<'_>,
+    Container<'_>,
+    Page<'_>,
+    Supports<'_>,
+    Layer<'_>,
+    FontFace<'_>,
+    Viewport<'_>,
+    MsViewport<'_>,
+    Keyframes<'_>,
+    Blocks<'_>,
+    KeyframeBlocks<'_>,
+    CssEntity<'_>,
+    CssEntities<'_>
+);
+
+impl WriteCss for Parameter<'_> {
+    fn write_css<W: CssWriter>(&self, w: &mut W) -> std::fmt::Result {
+        w.mark(self.location);
+        write!(w, "{}:{}", self.name, self.value)
+    }
+}
+
+impl WriteCss for Parameters<'_> {
+    fn write_css<W: CssWriter>(&self, w: &mut W) -> std::fmt::Result {
+        for (i, parameter) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(w, ";")?;
+            }
+            parameter.write_css(w)?;
         }
-        write!(f, " {{{}}}", self.parameters)?;
         Ok(())
     }
 }
 
-impl Display for Supports {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "@supports {}{{{}}}", self.conditions, self.entities)
+impl<'a> Block<'a> {
+    /// Block without selectors, which only holds declarations of the enclosing rule
+    pub fn is_bare(&self) -> bool {
+        self.selectors.is_empty()
     }
 }
 
-impl Display for FontFace {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "@font-face {{{}}}", self.parameters)
+impl WriteCss for Block<'_> {
+    fn write_css<W: CssWriter>(&self, w: &mut W) -> std::fmt::Result {
+        if self.is_bare() {
+            return self.parameters.write_css(w);
+        }
+        w.mark(self.location);
+        write!(w, "{}{{", self.selectors)?;
+        self.parameters.write_css(w)?;
+        if !self.entities.is_empty() {
+            if !self.parameters.is_empty() {
+                write!(w, ";")?;
+            }
+            self.entities.write_css(w)?;
+        }
+        write!(w, "}}")
     }
 }
 
-impl Display for Viewport {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "@viewport {{{}}}", self.parameters)
+impl WriteCss for KeyframeBlock<'_> {
+    fn write_css<W: CssWriter>(&self, w: &mut W) -> std::fmt::Result {
+        write!(w, "{}{{", self.name)?;
+        self.parameters.write_css(w)?;
+        write!(w, "}}")
     }
 }
 
-impl Display for MsViewport {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "@-ms-viewport {{{}}}", self.parameters)
+impl WriteCss for Media<'_> {
+    fn write_css<W: CssWriter>(&self, w: &mut W) -> std::fmt::Result {
+        write!(w, "@media {}{{", self.screen)?;
+        self.entities.write_css(w)?;
+        write!(w, "}}")
     }
 }
 
-impl Display for Keyframes {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+impl WriteCss for Container<'_> {
+    fn write_css<W: CssWriter>(&self, w: &mut W) -> std::fmt::Result {
+        write!(w, "@container ")?;
+        if let Some(name) = &self.name {
+            write!(w, "{} ", name)?;
+        }
+        write!(w, "{}{{", self.condition)?;
+        self.entities.write_css(w)?;
+        write!(w, "}}")
+    }
+}
+
+impl WriteCss for Page<'_> {
+    fn write_css<W: CssWriter>(&self, w: &mut W) -> std::fmt::Result {
+        write!(w, "@page")?;
+        if let Some(selectors) = &self.selectors {
+            write!(w, " {}", selectors)?
+        }
+        write!(w, " {{")?;
+        self.parameters.write_css(w)?;
+        write!(w, "}}")
+    }
+}
+
+impl WriteCss for Supports<'_> {
+    fn write_css<W: CssWriter>(&self, w: &mut W) -> std::fmt::Result {
+        write!(w, "@supports {}{{", self.conditions)?;
+        self.entities.write_css(w)?;
+        write!(w, "}}")
+    }
+}
+
+impl WriteCss for Layer<'_> {
+    fn write_css<W: CssWriter>(&self, w: &mut W) -> std::fmt::Result {
+        write!(w, "@layer")?;
+        if !self.names.is_empty() {
+            for (i, name) in self.names.iter().enumerate() {
+                write!(w, "{}{}", if i == 0 { " " } else { "," }, name)?;
+            }
+        }
+        match &self.entities {
+            Some(entities) => {
+                write!(w, "{{")?;
+                entities.write_css(w)?;
+                write!(w, "}}")
+            }
+            None => write!(w, ";"),
+        }
+    }
+}
+
+impl WriteCss for FontFace<'_> {
+    fn write_css<W: CssWriter>(&self, w: &mut W) -> std::fmt::Result {
+        write!(w, "@font-face {{")?;
+        self.parameters.write_css(w)?;
+        write!(w, "}}")
+    }
+}
+
+impl WriteCss for Viewport<'_> {
+    fn write_css<W: CssWriter>(&self, w: &mut W) -> std::fmt::Result {
+        write!(w, "@viewport {{")?;
+        self.parameters.write_css(w)?;
+        write!(w, "}}")
+    }
+}
+
+impl WriteCss for MsViewport<'_> {
+    fn write_css<W: CssWriter>(&self, w: &mut W) -> std::fmt::Result {
+        write!(w, "@-ms-viewport {{")?;
+        self.parameters.write_css(w)?;
+        write!(w, "}}")
+    }
+}
+
+impl WriteCss for Keyframes<'_> {
+    fn write_css<W: CssWriter>(&self, w: &mut W) -> std::fmt::Result {
         if self.webkit_prefix {
-            write!(f, "@-webkit-keyframes {}{{{}}}", self.name, self.blocks)
+            write!(w, "@-webkit-keyframes {}{{", self.name)?;
         } else {
-            write!(f, "@keyframes {}{{{}}}", self.name, self.blocks)
+            write!(w, "@keyframes {}{{", self.name)?;
         }
+        self.blocks.write_css(w)?;
+        write!(w, "}}")
     }
 }
 
-impl Display for Blocks {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            self.0
-                .iter()
-                .map(|block| format!("{}", block))
-                .collect::<Vec<String>>()
-                .join("")
-        )
+impl WriteCss for Blocks<'_> {
+    fn write_css<W: CssWriter>(&self, w: &mut W) -> std::fmt::Result {
+        self.0.iter().try_for_each(|block| block.write_css(w))
     }
 }
 
-impl Display for KeyframeBlocks {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            self.0
-                .iter()
-                .map(|block| format!("{}", block))
-                .collect::<Vec<String>>()
-                .join("")
-        )
+impl WriteCss for KeyframeBlocks<'_> {
+    fn write_css<W: CssWriter>(&self, w: &mut W) -> std::fmt::Result {
+        self.0.iter().try_for_each(|block| block.write_css(w))
     }
 }
 
-impl Display for NamespaceAt {
+impl Display for NamespaceAt<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "@namespace ")?;
         if let Some(prefix) = &self.prefix {
@@ -299,23 +924,23 @@ impl Display for NamespaceAt {
     }
 }
 
-impl Display for CharsetAt {
+impl Display for CharsetAt<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "@charset {};", self.charset)
     }
 }
 
-impl Display for ImportAt {
+impl Display for ImportAt<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "@import {};", self.url)?;
+        write!(f, "@import {}", self.url)?;
         if let Some(media_queries) = &self.media_queries {
             write!(f, " {}", media_queries)?
         }
-        Ok(())
+        write!(f, ";")
     }
 }
 
-impl Display for At {
+impl Display for At<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             At::Namespace(n) => {
@@ -329,32 +954,78 @@ impl Display for At {
     }
 }
 
-impl Display for CssEntities {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            self.0
-                .iter()
-                .map(|block| format!("{}", block))
-                .collect::<Vec<String>>()
-                .join("")
-        )
+impl WriteCss for CssEntity<'_> {
+    fn write_css<W: CssWriter>(&self, w: &mut W) -> std::fmt::Result {
+        match self {
+            CssEntity::Block(block) => block.write_css(w),
+            CssEntity::Media(media) => media.write_css(w),
+            CssEntity::Container(container) => container.write_css(w),
+            CssEntity::Page(page) => page.write_css(w),
+            CssEntity::Supports(supports) => supports.write_css(w),
+            CssEntity::Layer(layer) => layer.write_css(w),
+            CssEntity::FontFace(font_face) => font_face.write_css(w),
+            CssEntity::Viewport(viewport) => viewport.write_css(w),
+            CssEntity::MsViewport(viewport) => viewport.write_css(w),
+            CssEntity::Keyframes(keyframes) => keyframes.write_css(w),
+            CssEntity::At(at) => write!(w, "{}", at),
+        }
+    }
+}
+
+impl WriteCss for CssEntities<'_> {
+    fn write_css<W: CssWriter>(&self, w: &mut W) -> std::fmt::Result {
+        for (i, entity) in self.0.iter().enumerate() {
+            entity.write_css(w)?;
+            if matches!(entity, CssEntity::Block(block) if block.is_bare() && i + 1 < self.0.len())
+            {
+                write!(w, ";")?;
+            }
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::structure::{Block, Blocks, Selector, SelectorWithPseudoClasses};
+    use crate::optimizations::{Level, Minifier};
+    use crate::parse;
+    use crate::structure::{
+        At, Block, Blocks, CssEntity, ImportAt, IntoOwned, Parameters, Selector,
+    };
     use indexmap::map::IndexMap;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_borrowed() {
+        let input = String::from("a{color:red;margin:0 auto}.b{COLOR:#FFFFFF}");
+        let stylesheet = Minifier::default().optimize(parse(&input).unwrap(), Level::One);
+        fn parameters<'a>(entity: &CssEntity<'a>) -> Parameters<'a> {
+            match entity {
+                CssEntity::Block(block) => block.parameters.clone(),
+                entity => panic!("unexpected {:?}", entity),
+            }
+        }
+        let a = parameters(&stylesheet[0]);
+        assert!(matches!(a.get("color"), Some(Cow::Borrowed("red"))));
+        assert!(matches!(a.get("margin"), Some(Cow::Borrowed("0 auto"))));
+        let b = parameters(&stylesheet[1]);
+        assert!(matches!(b.get("color"), Some(Cow::Owned(color)) if color == "#fff"));
+
+        let owned = stylesheet.into_owned();
+        drop(input);
+        assert_eq!(
+            owned.to_string(),
+            "a{color:red;margin:0 auto}.b{color:#fff}"
+        );
+    }
 
     #[test]
     fn write_block() {
         let blocks: Blocks = vec![
             Block {
                 selectors: vec![
-                    SelectorWithPseudoClasses(Some(Selector::Id("some_id".into())), vec![]),
-                    SelectorWithPseudoClasses(Some(Selector::Tag("input".into())), vec![]),
+                    Selector::Id("some_id".into()).into(),
+                    Selector::Tag("input".into()).into(),
                 ]
                 .into(),
                 parameters: {
@@ -363,11 +1034,13 @@ mod test {
                     tmp.insert("color".into(), "white".into());
                     tmp.into()
                 },
+                entities: Default::default(),
+                location: Default::default(),
             },
             Block {
                 selectors: vec![
-                    SelectorWithPseudoClasses(Some(Selector::Id("some_id_2".into())), vec![]),
-                    SelectorWithPseudoClasses(Some(Selector::Class("class".into())), vec![]),
+                    Selector::Id("some_id_2".into()).into(),
+                    Selector::Class("class".into()).into(),
                 ]
                 .into(),
                 parameters: {
@@ -376,9 +1049,23 @@ mod test {
                     tmp.insert("color".into(), "black".into());
                     tmp.into()
                 },
+                entities: Default::default(),
+                location: Default::default(),
             },
         ]
         .into();
         assert_eq!(format!("{}", blocks), "#some_id,input{padding:5px 3px;color:white}#some_id_2,.class{padding:5px 4px;color:black}")
     }
+
+    #[test]
+    fn write_import() {
+        let import = At::Import(ImportAt {
+            url: "url('landscape.css')".into(),
+            media_queries: Some("screen and (orientation:landscape)".into()),
+        });
+        assert_eq!(
+            import.to_string(),
+            "@import url('landscape.css') screen and (orientation:landscape);"
+        )
+    }
 }